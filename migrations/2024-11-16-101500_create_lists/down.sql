@@ -0,0 +1,3 @@
+ALTER TABLE items DROP CONSTRAINT fk_item_list;
+ALTER TABLE items DROP COLUMN list_id;
+DROP TABLE lists;
@@ -0,0 +1,22 @@
+CREATE TABLE lists(
+  id UUID PRIMARY KEY,
+  group_id UUID NOT NULL,
+  name TEXT NOT NULL,
+  position INTEGER NOT NULL DEFAULT 0,
+  archived BOOLEAN NOT NULL DEFAULT false,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT fk_list_group FOREIGN KEY (group_id) REFERENCES groups(id) ON DELETE CASCADE,
+  UNIQUE(id, group_id)
+);
+
+INSERT INTO lists (id, group_id, name)
+SELECT gen_random_uuid(), g.id, 'Shopping list' FROM groups g;
+
+ALTER TABLE items ADD COLUMN list_id UUID;
+
+UPDATE items i SET list_id = l.id FROM lists l WHERE l.group_id = i.group_id;
+
+ALTER TABLE items ALTER COLUMN list_id SET NOT NULL;
+
+ALTER TABLE items
+  ADD CONSTRAINT fk_item_list FOREIGN KEY (list_id, group_id) REFERENCES lists(id, group_id) ON DELETE CASCADE;
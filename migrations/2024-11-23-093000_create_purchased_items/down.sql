@@ -0,0 +1,4 @@
+DROP TABLE purchased_items;
+ALTER TABLE items DROP CONSTRAINT fk_item_purchased_by;
+ALTER TABLE items DROP COLUMN purchased_at;
+ALTER TABLE items DROP COLUMN purchased_by;
@@ -0,0 +1,19 @@
+ALTER TABLE items
+  ADD COLUMN purchased_by UUID,
+  ADD COLUMN purchased_at TIMESTAMPTZ,
+  ADD CONSTRAINT fk_item_purchased_by FOREIGN KEY (purchased_by) REFERENCES users(id) ON DELETE SET NULL;
+
+CREATE TABLE purchased_items(
+  id UUID PRIMARY KEY,
+  product_id UUID NOT NULL,
+  group_id UUID NOT NULL,
+  list_id UUID NOT NULL,
+  unit product_unit DEFAULT 'pc',
+  quantity decimal DEFAULT 1,
+  purchased_by UUID,
+  purchased_at TIMESTAMPTZ NOT NULL,
+  cleared_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT fk_purchased_product FOREIGN KEY (product_id) REFERENCES products(id) ON DELETE CASCADE,
+  CONSTRAINT fk_purchased_list FOREIGN KEY (list_id, group_id) REFERENCES lists(id, group_id) ON DELETE CASCADE,
+  CONSTRAINT fk_purchased_user FOREIGN KEY (purchased_by) REFERENCES users(id) ON DELETE SET NULL
+)
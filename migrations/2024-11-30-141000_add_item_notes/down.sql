@@ -0,0 +1,2 @@
+ALTER TABLE purchased_items DROP COLUMN note;
+ALTER TABLE items DROP COLUMN note;
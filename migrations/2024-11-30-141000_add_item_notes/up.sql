@@ -0,0 +1,2 @@
+ALTER TABLE items ADD COLUMN note TEXT;
+ALTER TABLE purchased_items ADD COLUMN note TEXT;
@@ -0,0 +1,6 @@
+ALTER TABLE purchased_items ALTER COLUMN quantity DROP NOT NULL;
+ALTER TABLE purchased_items ALTER COLUMN unit DROP NOT NULL;
+
+ALTER TABLE items DROP CONSTRAINT positive_item_quantity;
+ALTER TABLE items ALTER COLUMN quantity DROP NOT NULL;
+ALTER TABLE items ALTER COLUMN unit DROP NOT NULL;
@@ -0,0 +1,10 @@
+UPDATE items SET unit = 'pc' WHERE unit IS NULL;
+UPDATE items SET quantity = 1 WHERE quantity IS NULL;
+ALTER TABLE items ALTER COLUMN unit SET NOT NULL;
+ALTER TABLE items ALTER COLUMN quantity SET NOT NULL;
+ALTER TABLE items ADD CONSTRAINT positive_item_quantity CHECK (quantity > 0);
+
+UPDATE purchased_items SET unit = 'pc' WHERE unit IS NULL;
+UPDATE purchased_items SET quantity = 1 WHERE quantity IS NULL;
+ALTER TABLE purchased_items ALTER COLUMN unit SET NOT NULL;
+ALTER TABLE purchased_items ALTER COLUMN quantity SET NOT NULL;
@@ -0,0 +1,3 @@
+ALTER TABLE user_group_join_requests
+  DROP COLUMN resolved_at,
+  DROP COLUMN created_at;
@@ -0,0 +1,3 @@
+ALTER TABLE user_group_join_requests
+  ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  ADD COLUMN resolved_at TIMESTAMPTZ;
@@ -0,0 +1,2 @@
+ALTER TABLE users_groups DROP COLUMN role;
+DROP TYPE group_role;
@@ -0,0 +1,7 @@
+CREATE TYPE group_role AS ENUM ('owner', 'admin', 'member', 'viewer');
+
+ALTER TABLE users_groups ADD COLUMN role group_role NOT NULL DEFAULT 'member';
+
+UPDATE users_groups ug SET role = 'owner'
+FROM groups g
+WHERE g.id = ug.group_id AND g.created_by_user = ug.user_id;
@@ -0,0 +1,14 @@
+CREATE TABLE group_invites(
+  id UUID PRIMARY KEY,
+  group_id UUID NOT NULL,
+  token TEXT NOT NULL UNIQUE,
+  created_by_user UUID NOT NULL,
+  role group_role NOT NULL DEFAULT 'member',
+  max_uses INTEGER,
+  uses INTEGER NOT NULL DEFAULT 0,
+  expires_at TIMESTAMPTZ,
+  revoked BOOLEAN NOT NULL DEFAULT false,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT fk_invite_group FOREIGN KEY (group_id) REFERENCES groups(id) ON DELETE CASCADE,
+  CONSTRAINT fk_invite_user FOREIGN KEY (created_by_user) REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,3 @@
+DROP INDEX group_invites_email_idx;
+
+ALTER TABLE group_invites DROP COLUMN email;
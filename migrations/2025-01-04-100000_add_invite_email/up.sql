@@ -0,0 +1,3 @@
+ALTER TABLE group_invites ADD COLUMN email TEXT;
+
+CREATE INDEX group_invites_email_idx ON group_invites (LOWER(email)) WHERE email IS NOT NULL;
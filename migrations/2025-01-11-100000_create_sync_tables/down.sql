@@ -0,0 +1,2 @@
+DROP TABLE user_sync_cursors;
+DROP TABLE events;
@@ -0,0 +1,21 @@
+CREATE TABLE events(
+  id UUID PRIMARY KEY,
+  sequence SERIAL,
+  group_id UUID,
+  recipient_id UUID,
+  payload JSONB NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT fk_event_group FOREIGN KEY (group_id) REFERENCES groups(id) ON DELETE CASCADE,
+  CONSTRAINT fk_event_recipient FOREIGN KEY (recipient_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX events_sequence_idx ON events (sequence);
+
+CREATE TABLE user_sync_cursors(
+  user_id UUID PRIMARY KEY,
+  direct_sequence INTEGER NOT NULL DEFAULT 0,
+  group_sequence INTEGER NOT NULL DEFAULT 0,
+  event_sequence INTEGER NOT NULL DEFAULT 0,
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT fk_sync_cursor_user FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,6 @@
+DELETE FROM user_sync_cursors WHERE device_id <> 'default';
+
+ALTER TABLE user_sync_cursors DROP CONSTRAINT user_sync_cursors_pkey;
+ALTER TABLE user_sync_cursors ADD PRIMARY KEY (user_id);
+
+ALTER TABLE user_sync_cursors DROP COLUMN device_id;
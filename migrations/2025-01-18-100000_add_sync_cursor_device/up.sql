@@ -0,0 +1,4 @@
+ALTER TABLE user_sync_cursors ADD COLUMN device_id TEXT NOT NULL DEFAULT 'default';
+
+ALTER TABLE user_sync_cursors DROP CONSTRAINT user_sync_cursors_pkey;
+ALTER TABLE user_sync_cursors ADD PRIMARY KEY (user_id, device_id);
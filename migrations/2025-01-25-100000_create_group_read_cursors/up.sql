@@ -0,0 +1,9 @@
+CREATE TABLE group_read_cursors(
+  user_id UUID NOT NULL,
+  group_id UUID NOT NULL,
+  sequence INTEGER NOT NULL DEFAULT 0,
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (user_id, group_id),
+  CONSTRAINT fk_read_cursor_user FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+  CONSTRAINT fk_read_cursor_group FOREIGN KEY (group_id) REFERENCES groups(id) ON DELETE CASCADE
+);
@@ -0,0 +1,2 @@
+ALTER TABLE purchased_items DROP CONSTRAINT purchased_item_quantity_limits;
+ALTER TABLE items DROP CONSTRAINT item_quantity_limits;
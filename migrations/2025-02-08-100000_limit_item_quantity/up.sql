@@ -0,0 +1,7 @@
+UPDATE items SET quantity = GREATEST(LEAST(ROUND(quantity, 3), 1000000), 0.001)
+WHERE quantity > 1000000 OR scale(quantity) > 3;
+ALTER TABLE items ADD CONSTRAINT item_quantity_limits CHECK (quantity <= 1000000 AND scale(quantity) <= 3);
+
+UPDATE purchased_items SET quantity = GREATEST(LEAST(ROUND(quantity, 3), 1000000), 0.001)
+WHERE quantity > 1000000 OR scale(quantity) > 3;
+ALTER TABLE purchased_items ADD CONSTRAINT purchased_item_quantity_limits CHECK (quantity <= 1000000 AND scale(quantity) <= 3);
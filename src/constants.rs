@@ -1 +1,8 @@
 pub static FAILED_TO_SEND_MESSAGE_TO_STATE_WORKER: &str = "Failed to send message to state worker";
+pub static DEFAULT_PAGE_LIMIT: i64 = 20;
+pub static DEFAULT_LIST_NAME: &str = "Shopping list";
+pub static JOIN_REQUEST_COOLDOWN_HOURS: i64 = 24;
+pub static WEBSOCKET_HEARTBEAT_INTERVAL_SECONDS: u64 = 5;
+pub static DEFAULT_WEBSOCKET_IDLE_TIMEOUT_SECONDS: u64 = 30;
+pub static SYNC_CHECKPOINT_INTERVAL_SECONDS: u64 = 10;
+pub static WEBSOCKET_TOKEN_PROTOCOL: &str = "bearer";
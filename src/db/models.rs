@@ -1,5 +1,23 @@
 pub mod chat_message;
+pub mod event;
 pub mod group;
+pub mod group_invite;
+pub mod group_read_cursor;
+pub mod inbox;
+pub mod item;
+pub mod list;
+pub mod product;
+pub mod purchased_item;
+pub mod sync_cursor;
 pub mod user;
+pub use event::Event;
 pub use group::Group;
+pub use group_invite::GroupInvite;
+pub use group_read_cursor::GroupReadCursor;
+pub use inbox::InboxEntry;
+pub use item::{Item, ItemWithProduct, SortOrder};
+pub use list::List;
+pub use product::Product;
+pub use purchased_item::PurchasedItem;
+pub use sync_cursor::SyncCursor;
 pub use user::User;
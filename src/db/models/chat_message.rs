@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 use tokio_postgres::{Client, Error, Row};
 use uuid::Uuid;
 
-use crate::messages::websocket::DirectChatMessageResponse;
+use crate::messages::websocket::{DirectChatMessageResponse, GroupChatMessageResponse};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DirectChatMessage {
@@ -11,6 +11,7 @@ pub struct DirectChatMessage {
     pub sender_id: Uuid,
     pub receiver_id: Uuid,
     pub read: bool,
+    pub sequence: i32,
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
@@ -22,6 +23,7 @@ impl From<DirectChatMessageResponse> for DirectChatMessage {
             sender_id: value.sender_id,
             receiver_id: value.receiver_id,
             read: false,
+            sequence: value.sequence,
             created_at: value.created_at,
         }
     }
@@ -35,62 +37,86 @@ impl DirectChatMessage {
             sender_id: row.get("sender"),
             receiver_id: row.get("receiver"),
             read: row.get("read"),
+            sequence: row.get("sequence"),
             created_at: row.get("created_at"),
         }
     }
 }
 
 impl DirectChatMessage {
-    pub async fn insert(&self, client: &Client) -> Result<(), Error> {
+    // Messages between two users, newest first, older than the `before` sequence if given.
+    pub async fn get_conversation(
+        client: &Client,
+        user_id: &Uuid,
+        peer_id: &Uuid,
+        before: Option<i32>,
+        limit: i64,
+    ) -> Result<Vec<DirectChatMessage>, Error> {
         let query = "
-            INSERT INTO messages (id, message, sender, receiver, read, created_at)
-            VALUES ($1, $2, $3, $4, $5, $6)";
+            SELECT * FROM messages
+            WHERE ((sender = $1 AND receiver = $2) OR (sender = $2 AND receiver = $1))
+            AND ($3::INTEGER IS NULL OR sequence < $3)
+            ORDER BY sequence DESC
+            LIMIT $4";
 
-        client
-            .execute(
-                query,
-                &[
-                    &self.id,
-                    &self.message,
-                    &self.sender_id,
-                    &self.receiver_id,
-                    &self.read,
-                    &self.created_at,
-                ],
-            )
+        let rows = client
+            .query(query, &[user_id, peer_id, &before, &limit])
             .await?;
 
-        Ok(())
+        Ok(rows.into_iter().map(DirectChatMessage::from_row).collect())
     }
 
-    pub async fn get_by_id(
+    pub async fn get_received_since(
         client: &Client,
-        message_id: Uuid,
-    ) -> Result<Option<DirectChatMessage>, Error> {
-        let query = "SELECT * FROM messages WHERE id = $1";
-
-        if let Some(row) = client.query_opt(query, &[&message_id]).await? {
-            Ok(Some(DirectChatMessage::from_row(row)))
-        } else {
-            Ok(None)
-        }
+        receiver_id: &Uuid,
+        sequence: i32,
+    ) -> Result<Vec<DirectChatMessage>, Error> {
+        let query = "
+            SELECT * FROM messages
+            WHERE receiver = $1 AND sequence > $2
+            ORDER BY sequence";
+
+        let rows = client.query(query, &[receiver_id, &sequence]).await?;
+
+        Ok(rows.into_iter().map(DirectChatMessage::from_row).collect())
     }
 
-    pub async fn get_paginated(
+    // Users the given user has exchanged direct messages with.
+    pub async fn get_peer_ids(client: &Client, user_id: &Uuid) -> Result<Vec<Uuid>, Error> {
+        let query = "
+            SELECT DISTINCT CASE WHEN sender = $1 THEN receiver ELSE sender END AS peer_id
+            FROM messages WHERE sender = $1 OR receiver = $1";
+
+        let rows = client.query(query, &[user_id]).await?;
+
+        Ok(rows.iter().map(|row| row.get("peer_id")).collect())
+    }
+
+    // Marks the messages the peer sent to the reader up to the sequence as read.
+    pub async fn mark_read(
         client: &Client,
-        limit: i64,
-        offset: i64,
-    ) -> Result<Vec<DirectChatMessage>, Error> {
+        reader_id: &Uuid,
+        peer_id: &Uuid,
+        up_to_sequence: i32,
+    ) -> Result<u64, Error> {
         let query = "
-            SELECT * FROM messages
-            ORDER BY created_at DESC
-            LIMIT $1 OFFSET $2";
+            UPDATE messages SET read = true
+            WHERE receiver = $1 AND sender = $2 AND sequence <= $3 AND NOT read";
 
-        let rows = client.query(query, &[&limit, &offset]).await?;
+        client
+            .execute(query, &[reader_id, peer_id, &up_to_sequence])
+            .await
+    }
 
-        let messages = rows.into_iter().map(DirectChatMessage::from_row).collect();
+    // Reserved before the broadcast, see `GroupChatMessage::reserve_sequences`.
+    pub async fn reserve_sequences(client: &Client, count: i64) -> Result<Vec<i32>, Error> {
+        let query = "SELECT nextval(pg_get_serial_sequence('messages', 'sequence'))::INTEGER
+            FROM generate_series(1, $1)";
+        let rows = client.query(query, &[&count]).await?;
 
-        Ok(messages)
+        let mut sequences: Vec<i32> = rows.iter().map(|row| row.get(0)).collect();
+        sequences.sort_unstable();
+        Ok(sequences)
     }
 
     pub async fn insert_bulk(client: &Client, messages: &[DirectChatMessage]) -> Result<(), Error> {
@@ -99,7 +125,93 @@ impl DirectChatMessage {
         }
 
         let mut query = String::from(
-            "INSERT INTO messages (id, message, sender, receiver, read, created_at) VALUES ",
+            "INSERT INTO messages (id, message, sender, receiver, read, sequence, created_at) VALUES ",
+        );
+        let mut params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = Vec::new();
+
+        for (i, message) in messages.iter().enumerate() {
+            if i > 0 {
+                query.push_str(", ");
+            }
+            let base = i * 7;
+            query.push_str(&format!(
+                "(${}, ${}, ${}, ${}, ${}, ${}, ${})",
+                base + 1,
+                base + 2,
+                base + 3,
+                base + 4,
+                base + 5,
+                base + 6,
+                base + 7
+            ));
+            params.push(&message.id);
+            params.push(&message.message);
+            params.push(&message.sender_id);
+            params.push(&message.receiver_id);
+            params.push(&message.read);
+            params.push(&message.sequence);
+            params.push(&message.created_at);
+        }
+
+        client.execute(query.as_str(), &params[..]).await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GroupChatMessage {
+    pub id: Uuid,
+    pub message: String,
+    pub sender_id: Uuid,
+    pub group_id: Uuid,
+    pub sequence: i32,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<GroupChatMessageResponse> for GroupChatMessage {
+    fn from(value: GroupChatMessageResponse) -> Self {
+        Self {
+            id: value.id,
+            message: value.message,
+            sender_id: value.sender_id,
+            group_id: value.group_id,
+            sequence: value.sequence,
+            created_at: value.created_at,
+        }
+    }
+}
+
+impl GroupChatMessage {
+    fn from_row(row: Row) -> Self {
+        GroupChatMessage {
+            id: row.get("id"),
+            message: row.get("message"),
+            sender_id: row.get("sender"),
+            group_id: row.get("to_group"),
+            sequence: row.get("sequence"),
+            created_at: row.get("created_at"),
+        }
+    }
+
+    // Messages are inserted in batches after they were broadcast, so sequences are reserved
+    // up front to give clients a stable position right away.
+    pub async fn reserve_sequences(client: &Client, count: i64) -> Result<Vec<i32>, Error> {
+        let query = "SELECT nextval(pg_get_serial_sequence('group_messages', 'sequence'))::INTEGER
+            FROM generate_series(1, $1)";
+        let rows = client.query(query, &[&count]).await?;
+
+        let mut sequences: Vec<i32> = rows.iter().map(|row| row.get(0)).collect();
+        sequences.sort_unstable();
+        Ok(sequences)
+    }
+
+    pub async fn insert_bulk(client: &Client, messages: &[GroupChatMessage]) -> Result<(), Error> {
+        if messages.is_empty() {
+            return Ok(()); // Nothing to insert
+        }
+
+        let mut query = String::from(
+            "INSERT INTO group_messages (id, message, sender, to_group, sequence, created_at) VALUES ",
         );
         let mut params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = Vec::new();
 
@@ -120,12 +232,45 @@ impl DirectChatMessage {
             params.push(&message.id);
             params.push(&message.message);
             params.push(&message.sender_id);
-            params.push(&message.receiver_id);
-            params.push(&message.read);
+            params.push(&message.group_id);
+            params.push(&message.sequence);
             params.push(&message.created_at);
         }
 
         client.execute(query.as_str(), &params[..]).await?;
         Ok(())
     }
+
+    pub async fn get_since(
+        client: &Client,
+        group_ids: &[Uuid],
+        sequence: i32,
+    ) -> Result<Vec<GroupChatMessage>, Error> {
+        let query = "
+            SELECT * FROM group_messages
+            WHERE to_group = ANY($1) AND sequence > $2
+            ORDER BY sequence";
+
+        let rows = client.query(query, &[&group_ids, &sequence]).await?;
+
+        Ok(rows.into_iter().map(GroupChatMessage::from_row).collect())
+    }
+
+    // Messages of a group, newest first, older than the `before` sequence if given.
+    pub async fn get_page(
+        client: &Client,
+        group_id: &Uuid,
+        before: Option<i32>,
+        limit: i64,
+    ) -> Result<Vec<GroupChatMessage>, Error> {
+        let query = "
+            SELECT * FROM group_messages
+            WHERE to_group = $1 AND ($2::INTEGER IS NULL OR sequence < $2)
+            ORDER BY sequence DESC
+            LIMIT $3";
+
+        let rows = client.query(query, &[group_id, &before, &limit]).await?;
+
+        Ok(rows.into_iter().map(GroupChatMessage::from_row).collect())
+    }
 }
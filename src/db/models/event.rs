@@ -0,0 +1,93 @@
+use deadpool_postgres::Client;
+use tokio_postgres::NoTls;
+
+use crate::messages::websocket::{Conversation, WebsocketMessageResponse};
+
+// A persisted copy of a broadcast that has no table of its own, kept so clients that were
+// offline can be caught up. Group events go to every member, the rest to a single recipient.
+#[derive(Debug)]
+pub struct Event {
+    pub id: uuid::Uuid,
+    pub group_id: Option<uuid::Uuid>,
+    pub recipient_id: Option<uuid::Uuid>,
+    pub payload: serde_json::Value,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Event {
+    pub fn from_response(response: &WebsocketMessageResponse) -> Option<Event> {
+        let (group_id, recipient_id) = match response {
+            WebsocketMessageResponse::AddItems(msg) => (Some(msg.group_id), None),
+            WebsocketMessageResponse::RemoveItems(msg) => (Some(msg.group_id), None),
+            WebsocketMessageResponse::UpdateItems(msg) => (Some(msg.group_id), None),
+            WebsocketMessageResponse::TogglePurchased(msg) => (Some(msg.group_id), None),
+            WebsocketMessageResponse::ClearPurchased(msg) => (Some(msg.group_id), None),
+            WebsocketMessageResponse::JoinGroup(msg) => (Some(msg.group_id), None),
+            WebsocketMessageResponse::ApproveJoin(msg) => (None, Some(msg.candidate_id)),
+            WebsocketMessageResponse::ReadReceipt(msg) => match msg.conversation {
+                Conversation::Direct { peer_id } => (None, Some(peer_id)),
+                Conversation::Group { group_id } => (Some(group_id), None),
+            },
+            _ => return None,
+        };
+
+        Some(Event {
+            id: uuid::Uuid::new_v4(),
+            group_id,
+            recipient_id,
+            payload: serde_json::to_value(response).expect("Failed to serialize event payload"),
+            created_at: chrono::Utc::now(),
+        })
+    }
+
+    pub fn parse_row(row: &tokio_postgres::Row) -> Event {
+        Event {
+            id: row.get("id"),
+            group_id: row.get("group_id"),
+            recipient_id: row.get("recipient_id"),
+            payload: row.get("payload"),
+            created_at: row.get("created_at"),
+        }
+    }
+
+    pub async fn insert_bulk(
+        client: &Client<NoTls>,
+        events: &[Event],
+    ) -> Result<(), tokio_postgres::Error> {
+        // Inserted one by one so sequences follow the order the events were broadcast in.
+        let stmt = "INSERT into events(id,group_id,recipient_id,payload,created_at)
+            VALUES($1,$2,$3,$4,$5)";
+        for event in events {
+            client
+                .execute(
+                    stmt,
+                    &[
+                        &event.id,
+                        &event.group_id,
+                        &event.recipient_id,
+                        &event.payload,
+                        &event.created_at,
+                    ],
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_since(
+        user_id: &uuid::Uuid,
+        group_ids: &[uuid::Uuid],
+        sequence: i32,
+        client: &Client<NoTls>,
+    ) -> Result<Vec<Event>, tokio_postgres::Error> {
+        let stmt = "SELECT * FROM events
+            WHERE sequence > $1 AND (group_id = ANY($2) OR recipient_id = $3)
+            ORDER BY sequence";
+        let rows = client
+            .query(stmt, &[&sequence, &group_ids, user_id])
+            .await?;
+
+        Ok(rows.iter().map(Event::parse_row).collect())
+    }
+}
@@ -1,9 +1,10 @@
 use deadpool_postgres::Client;
+use postgres_types::{FromSql, ToSql};
 use serde::Serialize;
-use std::fmt;
 use tokio_postgres::NoTls;
 
-use crate::http;
+use crate::permissions::GroupRole;
+use crate::{constants, http};
 
 use super::user::User;
 
@@ -41,10 +42,25 @@ impl Group {
             .await?;
 
         let user_group_id = uuid::Uuid::new_v4();
-        let stmt = "INSERT into users_groups(id,group_id,user_id) VALUES($1,$2,$3)";
+        let stmt = "INSERT into users_groups(id,group_id,user_id,role) VALUES($1,$2,$3,$4)";
 
         transaction
-            .execute(stmt, &[&user_group_id, &self.id, &self.created_by_user])
+            .execute(
+                stmt,
+                &[
+                    &user_group_id,
+                    &self.id,
+                    &self.created_by_user,
+                    &GroupRole::Owner,
+                ],
+            )
+            .await?;
+
+        let list_id = uuid::Uuid::new_v4();
+        let stmt = "INSERT into lists(id,group_id,name) VALUES($1,$2,$3)";
+
+        transaction
+            .execute(stmt, &[&list_id, &self.id, &constants::DEFAULT_LIST_NAME])
             .await?;
         transaction.commit().await?;
         Ok(())
@@ -60,6 +76,77 @@ impl Group {
         Ok(rows.first().map(Group::parse_row))
     }
 
+    pub async fn rename(
+        group_id: &uuid::Uuid,
+        name: &str,
+        client: &Client<NoTls>,
+    ) -> Result<u64, tokio_postgres::Error> {
+        let stmt = "UPDATE groups SET name = $1 WHERE id = $2";
+        let rows_affected = client.execute(stmt, &[&name, group_id]).await?;
+
+        Ok(rows_affected)
+    }
+
+    pub async fn transfer_ownership(
+        group_id: &uuid::Uuid,
+        old_owner_id: &uuid::Uuid,
+        new_owner_id: &uuid::Uuid,
+        client: &mut Client<NoTls>,
+    ) -> Result<u64, tokio_postgres::Error> {
+        let transaction = client.transaction().await?;
+        let stmt = "UPDATE groups SET created_by_user = $1 WHERE id = $2
+            AND EXISTS (SELECT 1 FROM users_groups WHERE group_id = $2 AND user_id = $1)";
+        let rows_affected = transaction.execute(stmt, &[new_owner_id, group_id]).await?;
+
+        if rows_affected == 0 {
+            return Ok(0);
+        }
+
+        let stmt = "UPDATE users_groups SET role = $1 WHERE group_id = $2 AND user_id = $3";
+        transaction
+            .execute(stmt, &[&GroupRole::Owner, group_id, new_owner_id])
+            .await?;
+        transaction
+            .execute(stmt, &[&GroupRole::Admin, group_id, old_owner_id])
+            .await?;
+
+        transaction.commit().await?;
+        Ok(rows_affected)
+    }
+
+    pub async fn set_member_role(
+        group_id: &uuid::Uuid,
+        user_id: &uuid::Uuid,
+        role: GroupRole,
+        client: &Client<NoTls>,
+    ) -> Result<u64, tokio_postgres::Error> {
+        let stmt = "UPDATE users_groups SET role = $1 WHERE group_id = $2 AND user_id = $3";
+        let rows_affected = client.execute(stmt, &[&role, group_id, user_id]).await?;
+
+        Ok(rows_affected)
+    }
+
+    pub async fn delete(
+        group_id: &uuid::Uuid,
+        client: &Client<NoTls>,
+    ) -> Result<u64, tokio_postgres::Error> {
+        let stmt = "DELETE FROM groups WHERE id = $1";
+        let rows_affected = client.execute(stmt, &[group_id]).await?;
+
+        Ok(rows_affected)
+    }
+
+    pub async fn remove_member(
+        group_id: &uuid::Uuid,
+        user_id: &uuid::Uuid,
+        client: &Client<NoTls>,
+    ) -> Result<u64, tokio_postgres::Error> {
+        let stmt = "DELETE FROM users_groups WHERE group_id = $1 AND user_id = $2";
+        let rows_affected = client.execute(stmt, &[group_id, user_id]).await?;
+
+        Ok(rows_affected)
+    }
+
     pub async fn get_users(
         group_id: &uuid::Uuid,
         client: &Client<NoTls>,
@@ -76,36 +163,83 @@ impl Group {
             .collect())
     }
 
+    pub async fn get_group_request(
+        group_id: &uuid::Uuid,
+        user_id: &uuid::Uuid,
+        client: &Client<NoTls>,
+    ) -> Result<Option<JoinRequest>, tokio_postgres::Error> {
+        let stmt = "SELECT * FROM user_group_join_requests WHERE group_id = $1 AND user_id = $2";
+        let rows = client.query(stmt, &[group_id, user_id]).await?;
+
+        Ok(rows.first().map(JoinRequest::parse_row))
+    }
+
     pub async fn create_group_request(
         group_id: &uuid::Uuid,
         user_id: &uuid::Uuid,
         client: &Client<NoTls>,
     ) -> Result<(), tokio_postgres::Error> {
-        let stmt = "INSERT into user_group_join_requests(group_id,user_id) VALUES($1,$2)";
+        let stmt = "INSERT into user_group_join_requests(group_id,user_id) VALUES($1,$2)
+            ON CONFLICT (user_id, group_id) DO UPDATE
+            SET approved = 'unhandled', created_at = CURRENT_TIMESTAMP, resolved_at = NULL";
 
         client.execute(stmt, &[group_id, user_id]).await?;
 
         Ok(())
     }
 
+    pub async fn cancel_group_request(
+        group_id: &uuid::Uuid,
+        user_id: &uuid::Uuid,
+        client: &Client<NoTls>,
+    ) -> Result<u64, tokio_postgres::Error> {
+        let stmt = "DELETE FROM user_group_join_requests
+            WHERE group_id = $1 AND user_id = $2 AND approved = 'unhandled'";
+        let rows_affected = client.execute(stmt, &[group_id, user_id]).await?;
+
+        Ok(rows_affected)
+    }
+
     pub async fn handle_group_request(
         group_id: &uuid::Uuid,
         user_id: &uuid::Uuid,
         resolution: ApproveJoinResolution,
-        client: &Client<NoTls>,
+        client: &mut Client<NoTls>,
     ) -> Result<u64, tokio_postgres::Error> {
-        let stmt = "UPDATE user_group_join_requests SET approved = $1 WHERE user_id = $2 AND group_id = $3";
-        let rows_affected = client
-            .execute(stmt, &[&resolution.to_string(), user_id, group_id])
+        let transaction = client.transaction().await?;
+        let stmt =
+            "UPDATE user_group_join_requests SET approved = $1, resolved_at = CURRENT_TIMESTAMP
+            WHERE user_id = $2 AND group_id = $3 AND approved = 'unhandled'";
+        let rows_affected = transaction
+            .execute(stmt, &[&resolution, user_id, group_id])
             .await?;
 
+        if rows_affected == 0 {
+            return Ok(0);
+        }
+
+        if resolution == ApproveJoinResolution::Approved {
+            let user_group_id = uuid::Uuid::new_v4();
+            let stmt = "INSERT into users_groups(id,group_id,user_id) SELECT $1,$2,$3
+                WHERE NOT EXISTS (SELECT 1 FROM users_groups WHERE group_id = $2 AND user_id = $3)";
+            transaction
+                .execute(stmt, &[&user_group_id, group_id, user_id])
+                .await?;
+        }
+
+        transaction.commit().await?;
         Ok(rows_affected)
     }
 }
 
-enum ApproveJoinResolution {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ToSql, FromSql)]
+#[postgres(name = "approval")]
+pub enum ApproveJoinResolution {
+    #[postgres(name = "approved")]
     Approved,
+    #[postgres(name = "unhandled")]
     Unhandled,
+    #[postgres(name = "unapproved")]
     Unapproved,
 }
 
@@ -119,12 +253,21 @@ impl From<bool> for ApproveJoinResolution {
     }
 }
 
-impl fmt::Display for ApproveJoinResolution {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            ApproveJoinResolution::Approved => write!(f, "approved"),
-            ApproveJoinResolution::Unhandled => write!(f, "unhandled"),
-            ApproveJoinResolution::Unapproved => write!(f, "unapproved"),
+#[derive(Debug)]
+pub struct JoinRequest {
+    pub approved: ApproveJoinResolution,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub resolved_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl JoinRequest {
+    pub fn parse_row(row: &tokio_postgres::Row) -> JoinRequest {
+        JoinRequest {
+            approved: row
+                .get::<_, Option<ApproveJoinResolution>>("approved")
+                .unwrap_or(ApproveJoinResolution::Unhandled),
+            created_at: row.get("created_at"),
+            resolved_at: row.get("resolved_at"),
         }
     }
 }
@@ -0,0 +1,175 @@
+use deadpool_postgres::Client;
+use serde::Serialize;
+use tokio_postgres::NoTls;
+
+use crate::permissions::GroupRole;
+
+#[derive(Debug, Serialize)]
+pub struct GroupInvite {
+    pub id: uuid::Uuid,
+    pub group_id: uuid::Uuid,
+    pub token: String,
+    pub created_by_user: uuid::Uuid,
+    pub role: GroupRole,
+    pub max_uses: Option<i32>,
+    pub uses: i32,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub revoked: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub email: Option<String>,
+}
+
+impl GroupInvite {
+    pub fn new(
+        group_id: uuid::Uuid,
+        created_by_user: uuid::Uuid,
+        role: GroupRole,
+        max_uses: Option<i32>,
+        expires_at: Option<chrono::DateTime<chrono::Utc>>,
+        email: Option<String>,
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4(),
+            group_id,
+            token: uuid::Uuid::new_v4().simple().to_string(),
+            created_by_user,
+            role,
+            max_uses,
+            uses: 0,
+            expires_at,
+            revoked: false,
+            created_at: chrono::Utc::now(),
+            email,
+        }
+    }
+
+    pub fn parse_row(row: &tokio_postgres::Row) -> GroupInvite {
+        GroupInvite {
+            id: row.get("id"),
+            group_id: row.get("group_id"),
+            token: row.get("token"),
+            created_by_user: row.get("created_by_user"),
+            role: row.get("role"),
+            max_uses: row.get("max_uses"),
+            uses: row.get("uses"),
+            expires_at: row.get("expires_at"),
+            revoked: row.get("revoked"),
+            created_at: row.get("created_at"),
+            email: row.get("email"),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= chrono::Utc::now())
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.max_uses.is_some_and(|max_uses| self.uses >= max_uses)
+    }
+
+    pub async fn insert(&self, client: &Client<NoTls>) -> Result<(), tokio_postgres::Error> {
+        let stmt = "INSERT into group_invites(id,group_id,token,created_by_user,role,max_uses,uses,expires_at,revoked,created_at,email)
+            VALUES($1,$2,$3,$4,$5,$6,$7,$8,$9,$10,$11)";
+        client
+            .execute(
+                stmt,
+                &[
+                    &self.id,
+                    &self.group_id,
+                    &self.token,
+                    &self.created_by_user,
+                    &self.role,
+                    &self.max_uses,
+                    &self.uses,
+                    &self.expires_at,
+                    &self.revoked,
+                    &self.created_at,
+                    &self.email,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete(
+        invite_id: &uuid::Uuid,
+        client: &Client<NoTls>,
+    ) -> Result<u64, tokio_postgres::Error> {
+        let stmt = "DELETE FROM group_invites WHERE id = $1";
+        let rows_affected = client.execute(stmt, &[invite_id]).await?;
+
+        Ok(rows_affected)
+    }
+
+    pub async fn get_by_token(
+        token: &str,
+        client: &Client<NoTls>,
+    ) -> Result<Option<GroupInvite>, tokio_postgres::Error> {
+        let stmt = "SELECT * FROM group_invites WHERE token = $1";
+        let rows = client.query(stmt, &[&token]).await?;
+
+        Ok(rows.first().map(GroupInvite::parse_row))
+    }
+
+    pub async fn get_by_group(
+        group_id: &uuid::Uuid,
+        client: &Client<NoTls>,
+    ) -> Result<Vec<GroupInvite>, tokio_postgres::Error> {
+        let stmt = "SELECT * FROM group_invites WHERE group_id = $1 AND NOT revoked
+            ORDER BY created_at DESC";
+        let rows = client.query(stmt, &[group_id]).await?;
+
+        Ok(rows.iter().map(GroupInvite::parse_row).collect())
+    }
+
+    pub async fn revoke(
+        invite_id: &uuid::Uuid,
+        group_id: &uuid::Uuid,
+        client: &Client<NoTls>,
+    ) -> Result<u64, tokio_postgres::Error> {
+        let stmt = "UPDATE group_invites SET revoked = true
+            WHERE id = $1 AND group_id = $2 AND NOT revoked";
+        let rows_affected = client.execute(stmt, &[invite_id, group_id]).await?;
+
+        Ok(rows_affected)
+    }
+
+    pub async fn redeem(
+        &self,
+        user_id: &uuid::Uuid,
+        client: &mut Client<NoTls>,
+    ) -> Result<u64, tokio_postgres::Error> {
+        let transaction = client.transaction().await?;
+        let stmt = "UPDATE group_invites SET uses = uses + 1
+            WHERE id = $1 AND NOT revoked
+            AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+            AND (max_uses IS NULL OR uses < max_uses)";
+        let rows_affected = transaction.execute(stmt, &[&self.id]).await?;
+
+        if rows_affected == 0 {
+            return Ok(0);
+        }
+
+        let user_group_id = uuid::Uuid::new_v4();
+        let stmt = "INSERT into users_groups(id,group_id,user_id,role) SELECT $1,$2,$3,$4
+            WHERE NOT EXISTS (SELECT 1 FROM users_groups WHERE group_id = $2 AND user_id = $3)";
+        let rows_affected = transaction
+            .execute(stmt, &[&user_group_id, &self.group_id, user_id, &self.role])
+            .await?;
+
+        if rows_affected == 0 {
+            return Ok(0);
+        }
+
+        let stmt =
+            "UPDATE user_group_join_requests SET approved = 'approved', resolved_at = CURRENT_TIMESTAMP
+            WHERE user_id = $1 AND group_id = $2 AND approved = 'unhandled'";
+        transaction
+            .execute(stmt, &[user_id, &self.group_id])
+            .await?;
+
+        transaction.commit().await?;
+        Ok(rows_affected)
+    }
+}
@@ -0,0 +1,25 @@
+use deadpool_postgres::Client;
+use tokio_postgres::NoTls;
+
+// Sequence in group_messages up to which a member has read the group chat.
+#[derive(Debug)]
+pub struct GroupReadCursor {
+    pub user_id: uuid::Uuid,
+    pub group_id: uuid::Uuid,
+    pub sequence: i32,
+}
+
+impl GroupReadCursor {
+    // Cursors only move forward, a receipt for older messages arriving late is ignored.
+    pub async fn advance(&self, client: &Client<NoTls>) -> Result<(), tokio_postgres::Error> {
+        let stmt = "INSERT into group_read_cursors(user_id,group_id,sequence) VALUES($1,$2,$3)
+            ON CONFLICT (user_id, group_id) DO UPDATE SET
+                sequence = GREATEST(group_read_cursors.sequence, EXCLUDED.sequence),
+                updated_at = CURRENT_TIMESTAMP";
+        client
+            .execute(stmt, &[&self.user_id, &self.group_id, &self.sequence])
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+use deadpool_postgres::Client;
+use tokio_postgres::NoTls;
+
+use crate::messages::websocket::Conversation;
+
+#[derive(Debug)]
+pub struct InboxMessage {
+    pub message: String,
+    pub sender_id: uuid::Uuid,
+    pub sequence: i32,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+// One conversation of a user with its latest message and the number of messages they have not
+// read yet. Groups without messages have no latest message.
+#[derive(Debug)]
+pub struct InboxEntry {
+    pub conversation: Conversation,
+    pub last_message: Option<InboxMessage>,
+    pub unread_count: i64,
+}
+
+impl InboxEntry {
+    fn parse_row(conversation: Conversation, row: &tokio_postgres::Row) -> InboxEntry {
+        let last_message = row
+            .get::<_, Option<i32>>("sequence")
+            .map(|sequence| InboxMessage {
+                message: row.get("message"),
+                sender_id: row.get("sender"),
+                sequence,
+                created_at: row.get("created_at"),
+            });
+
+        InboxEntry {
+            conversation,
+            last_message,
+            unread_count: row.get("unread_count"),
+        }
+    }
+
+    pub async fn get_direct(
+        user_id: &uuid::Uuid,
+        client: &Client<NoTls>,
+    ) -> Result<Vec<InboxEntry>, tokio_postgres::Error> {
+        let stmt = "SELECT DISTINCT ON (peer_id) peer_id, message, sender, sequence, created_at,
+                (SELECT COUNT(*) FROM messages unread
+                    WHERE unread.sender = conversation.peer_id AND unread.receiver = $1
+                    AND unread.read IS NOT TRUE) AS unread_count
+            FROM (
+                SELECT CASE WHEN sender = $1 THEN receiver ELSE sender END AS peer_id,
+                    message, sender, sequence, created_at
+                FROM messages WHERE sender = $1 OR receiver = $1
+            ) conversation
+            ORDER BY peer_id, sequence DESC";
+        let rows = client.query(stmt, &[user_id]).await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| {
+                InboxEntry::parse_row(
+                    Conversation::Direct {
+                        peer_id: row.get("peer_id"),
+                    },
+                    row,
+                )
+            })
+            .collect())
+    }
+
+    pub async fn get_groups(
+        user_id: &uuid::Uuid,
+        client: &Client<NoTls>,
+    ) -> Result<Vec<InboxEntry>, tokio_postgres::Error> {
+        let stmt = "SELECT users_groups.group_id,
+                last.message, last.sender, last.sequence, last.created_at,
+                (SELECT COUNT(*) FROM group_messages unread
+                    WHERE unread.to_group = users_groups.group_id AND unread.sender <> $1
+                    AND unread.sequence > COALESCE(group_read_cursors.sequence, 0)) AS unread_count
+            FROM users_groups
+            LEFT JOIN group_read_cursors ON group_read_cursors.group_id = users_groups.group_id
+                AND group_read_cursors.user_id = $1
+            LEFT JOIN LATERAL (
+                SELECT message, sender, sequence, created_at FROM group_messages
+                WHERE to_group = users_groups.group_id
+                ORDER BY sequence DESC LIMIT 1
+            ) last ON true
+            WHERE users_groups.user_id = $1";
+        let rows = client.query(stmt, &[user_id]).await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| {
+                InboxEntry::parse_row(
+                    Conversation::Group {
+                        group_id: row.get("group_id"),
+                    },
+                    row,
+                )
+            })
+            .collect())
+    }
+}
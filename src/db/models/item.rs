@@ -0,0 +1,283 @@
+use deadpool_postgres::Client;
+use serde::Serialize;
+use tokio_postgres::NoTls;
+
+use crate::messages::websocket::{AddItemResponse, UpdateItemMessage};
+use crate::units::{ProductUnit, Quantity};
+
+#[derive(Debug, Serialize)]
+pub struct Item {
+    pub id: uuid::Uuid,
+    pub product_id: uuid::Uuid,
+    pub group_id: uuid::Uuid,
+    pub list_id: uuid::Uuid,
+    pub unit: ProductUnit,
+    pub quantity: Quantity,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ItemWithProduct {
+    pub id: uuid::Uuid,
+    pub product_id: uuid::Uuid,
+    pub product_name: String,
+    pub group_id: uuid::Uuid,
+    pub list_id: uuid::Uuid,
+    pub unit: ProductUnit,
+    pub quantity: Quantity,
+    pub purchased_by: Option<uuid::Uuid>,
+    pub purchased_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl From<AddItemResponse> for Item {
+    fn from(value: AddItemResponse) -> Self {
+        Self {
+            id: value.id,
+            product_id: value.product_id,
+            group_id: value.group_id,
+            list_id: value.list_id,
+            unit: value.product_unit,
+            quantity: value.quantity,
+        }
+    }
+}
+
+impl ItemWithProduct {
+    pub fn parse_row(row: &tokio_postgres::Row) -> ItemWithProduct {
+        ItemWithProduct {
+            id: row.get("id"),
+            product_id: row.get("product_id"),
+            product_name: row.get("product_name"),
+            group_id: row.get("group_id"),
+            list_id: row.get("list_id"),
+            unit: row.get("unit"),
+            quantity: row.get("quantity"),
+            purchased_by: row.get("purchased_by"),
+            purchased_at: row.get("purchased_at"),
+            note: row.get("note"),
+        }
+    }
+}
+
+impl Item {
+    pub fn parse_row(row: &tokio_postgres::Row) -> Item {
+        Item {
+            id: row.get("id"),
+            product_id: row.get("product_id"),
+            group_id: row.get("group_id"),
+            list_id: row.get("list_id"),
+            unit: row.get("unit"),
+            quantity: row.get("quantity"),
+        }
+    }
+
+    pub async fn get_unpurchased_by_products(
+        group_id: &uuid::Uuid,
+        list_id: &uuid::Uuid,
+        product_ids: &[uuid::Uuid],
+        client: &Client<NoTls>,
+    ) -> Result<Vec<Item>, tokio_postgres::Error> {
+        let stmt = "SELECT id, product_id, group_id, list_id, unit, quantity
+            FROM items
+            WHERE group_id = $1 AND list_id = $2 AND product_id = ANY($3)
+                AND purchased_at IS NULL
+            ORDER BY id";
+
+        Ok(client
+            .query(stmt, &[group_id, list_id, &product_ids])
+            .await?
+            .iter()
+            .map(Item::parse_row)
+            .collect())
+    }
+
+    pub async fn get_by_group(
+        group_id: &uuid::Uuid,
+        list_id: Option<&uuid::Uuid>,
+        product_name: Option<&str>,
+        sort_order: SortOrder,
+        client: &Client<NoTls>,
+    ) -> Result<Vec<ItemWithProduct>, tokio_postgres::Error> {
+        let order = match sort_order {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        };
+        let stmt = format!(
+            "SELECT i.id, i.product_id, p.name AS product_name, i.group_id, i.list_id,
+                i.unit, i.quantity, i.purchased_by, i.purchased_at, i.note
+             FROM items i
+             JOIN products p ON p.id = i.product_id
+             WHERE i.group_id = $1
+                AND ($2::uuid IS NULL OR i.list_id = $2)
+                AND ($3::text IS NULL OR p.name ILIKE $3 ESCAPE '\\')
+             ORDER BY p.name {}, i.id",
+            order
+        );
+
+        let name_pattern = product_name.map(|name| format!("%{}%", escape_like(name)));
+
+        Ok(client
+            .query(stmt.as_str(), &[group_id, &list_id, &name_pattern])
+            .await?
+            .iter()
+            .map(ItemWithProduct::parse_row)
+            .collect())
+    }
+
+    pub async fn insert_bulk(
+        client: &Client<NoTls>,
+        items: &[Item],
+    ) -> Result<(), tokio_postgres::Error> {
+        if items.is_empty() {
+            return Ok(());
+        }
+
+        let mut query = String::from(
+            "INSERT INTO items (id, product_id, group_id, list_id, unit, quantity) VALUES ",
+        );
+        let mut params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = Vec::new();
+
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                query.push_str(", ");
+            }
+            let base = i * 6;
+            query.push_str(&format!(
+                "(${}, ${}, ${}, ${}, ${}, ${})",
+                base + 1,
+                base + 2,
+                base + 3,
+                base + 4,
+                base + 5,
+                base + 6
+            ));
+            params.push(&item.id);
+            params.push(&item.product_id);
+            params.push(&item.group_id);
+            params.push(&item.list_id);
+            params.push(&item.unit);
+            params.push(&item.quantity);
+        }
+
+        client.execute(query.as_str(), &params[..]).await?;
+        Ok(())
+    }
+
+    pub async fn delete_bulk(
+        client: &Client<NoTls>,
+        group_id: &uuid::Uuid,
+        list_id: &uuid::Uuid,
+        item_ids: &[uuid::Uuid],
+    ) -> Result<u64, tokio_postgres::Error> {
+        if item_ids.is_empty() {
+            return Ok(0);
+        }
+
+        let stmt = "DELETE FROM items WHERE group_id = $1 AND list_id = $2 AND id = ANY($3)";
+        let rows_affected = client
+            .execute(stmt, &[group_id, list_id, &item_ids])
+            .await?;
+
+        Ok(rows_affected)
+    }
+
+    pub async fn update(
+        client: &Client<NoTls>,
+        group_id: &uuid::Uuid,
+        list_id: &uuid::Uuid,
+        item: &UpdateItemMessage,
+    ) -> Result<u64, tokio_postgres::Error> {
+        // An empty note clears it, a missing field leaves the column untouched.
+        let stmt = "UPDATE items SET
+                unit = COALESCE($1, unit),
+                quantity = COALESCE($2, quantity),
+                note = CASE WHEN $3::text IS NULL THEN note ELSE NULLIF($3, '') END
+            WHERE id = $4 AND group_id = $5 AND list_id = $6";
+        let rows_affected = client
+            .execute(
+                stmt,
+                &[
+                    &item.product_unit,
+                    &item.quantity,
+                    &item.note,
+                    &item.item_id,
+                    group_id,
+                    list_id,
+                ],
+            )
+            .await?;
+
+        Ok(rows_affected)
+    }
+
+    pub async fn set_purchased(
+        client: &Client<NoTls>,
+        group_id: &uuid::Uuid,
+        list_id: &uuid::Uuid,
+        item_id: &uuid::Uuid,
+        purchased_by: Option<&uuid::Uuid>,
+        purchased_at: Option<&chrono::DateTime<chrono::Utc>>,
+    ) -> Result<u64, tokio_postgres::Error> {
+        let stmt = "UPDATE items SET purchased_by = $1, purchased_at = $2
+            WHERE id = $3 AND group_id = $4 AND list_id = $5";
+        let rows_affected = client
+            .execute(
+                stmt,
+                &[&purchased_by, &purchased_at, item_id, group_id, list_id],
+            )
+            .await?;
+
+        Ok(rows_affected)
+    }
+
+    pub async fn clear_purchased(
+        client: &Client<NoTls>,
+        group_id: &uuid::Uuid,
+        list_id: &uuid::Uuid,
+        cleared_at: &chrono::DateTime<chrono::Utc>,
+    ) -> Result<u64, tokio_postgres::Error> {
+        let stmt = "
+            WITH cleared AS (
+                DELETE FROM items
+                WHERE group_id = $1 AND list_id = $2
+                    AND purchased_at IS NOT NULL AND purchased_at <= $3
+                RETURNING *
+            )
+            INSERT INTO purchased_items
+                (id, product_id, group_id, list_id, unit, quantity, note, purchased_by, purchased_at, cleared_at)
+            SELECT id, product_id, group_id, list_id, unit, quantity, note, purchased_by, purchased_at, $3
+            FROM cleared";
+        let rows_affected = client
+            .execute(stmt, &[group_id, list_id, cleared_at])
+            .await?;
+
+        Ok(rows_affected)
+    }
+}
+
+// Searches match the name literally, so wildcards typed by the user are escaped.
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_like_escapes_wildcards_and_the_escape_character() {
+        assert_eq!(escape_like("milk"), "milk");
+        assert_eq!(escape_like("100%"), "100\\%");
+        assert_eq!(escape_like("a_b"), "a\\_b");
+        assert_eq!(escape_like("c:\\d"), "c:\\\\d");
+    }
+}
@@ -0,0 +1,136 @@
+use deadpool_postgres::Client;
+use serde::Serialize;
+use tokio_postgres::NoTls;
+
+#[derive(Debug, Serialize)]
+pub struct List {
+    pub id: uuid::Uuid,
+    pub group_id: uuid::Uuid,
+    pub name: String,
+    pub position: i32,
+    pub archived: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl List {
+    pub fn new(group_id: uuid::Uuid, name: String) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4(),
+            group_id,
+            name,
+            position: 0,
+            archived: false,
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    pub fn parse_row(row: &tokio_postgres::Row) -> List {
+        List {
+            id: row.get("id"),
+            group_id: row.get("group_id"),
+            name: row.get("name"),
+            position: row.get("position"),
+            archived: row.get("archived"),
+            created_at: row.get("created_at"),
+        }
+    }
+
+    pub async fn insert(&mut self, client: &Client<NoTls>) -> Result<(), tokio_postgres::Error> {
+        let stmt = "INSERT into lists(id,group_id,name,position,archived,created_at)
+            SELECT $1, $2, $3, COALESCE(MAX(position) + 1, 0), $4, $5 FROM lists WHERE group_id = $2
+            RETURNING position";
+        let row = client
+            .query_one(
+                stmt,
+                &[
+                    &self.id,
+                    &self.group_id,
+                    &self.name,
+                    &self.archived,
+                    &self.created_at,
+                ],
+            )
+            .await?;
+        self.position = row.get("position");
+        Ok(())
+    }
+
+    pub async fn get_by_id(
+        list_id: &uuid::Uuid,
+        client: &Client<NoTls>,
+    ) -> Result<Option<List>, tokio_postgres::Error> {
+        let stmt = "SELECT * FROM lists WHERE id = $1";
+
+        Ok(client
+            .query_opt(stmt, &[list_id])
+            .await?
+            .as_ref()
+            .map(List::parse_row))
+    }
+
+    pub async fn get_by_group(
+        group_id: &uuid::Uuid,
+        include_archived: bool,
+        client: &Client<NoTls>,
+    ) -> Result<Vec<List>, tokio_postgres::Error> {
+        let stmt = "SELECT * FROM lists WHERE group_id = $1 AND ($2 OR NOT archived)
+            ORDER BY position, created_at";
+
+        Ok(client
+            .query(stmt, &[group_id, &include_archived])
+            .await?
+            .iter()
+            .map(List::parse_row)
+            .collect())
+    }
+
+    pub async fn rename(
+        list_id: &uuid::Uuid,
+        group_id: &uuid::Uuid,
+        name: &str,
+        client: &Client<NoTls>,
+    ) -> Result<u64, tokio_postgres::Error> {
+        let stmt = "UPDATE lists SET name = $1 WHERE id = $2 AND group_id = $3";
+        let rows_affected = client.execute(stmt, &[&name, list_id, group_id]).await?;
+
+        Ok(rows_affected)
+    }
+
+    pub async fn set_archived(
+        list_id: &uuid::Uuid,
+        group_id: &uuid::Uuid,
+        archived: bool,
+        client: &Client<NoTls>,
+    ) -> Result<u64, tokio_postgres::Error> {
+        let stmt = "UPDATE lists SET archived = $1 WHERE id = $2 AND group_id = $3";
+        let rows_affected = client
+            .execute(stmt, &[&archived, list_id, group_id])
+            .await?;
+
+        Ok(rows_affected)
+    }
+
+    pub async fn reorder(
+        group_id: &uuid::Uuid,
+        list_ids: &[uuid::Uuid],
+        client: &mut Client<NoTls>,
+    ) -> Result<u64, tokio_postgres::Error> {
+        let transaction = client.transaction().await?;
+        let stmt = "UPDATE lists SET position = $1 WHERE id = $2 AND group_id = $3";
+
+        let mut rows_affected = 0;
+        for (position, list_id) in list_ids.iter().enumerate() {
+            rows_affected += transaction
+                .execute(stmt, &[&(position as i32), list_id, group_id])
+                .await?;
+        }
+
+        if rows_affected != list_ids.len() as u64 {
+            transaction.rollback().await?;
+            return Ok(0);
+        }
+
+        transaction.commit().await?;
+        Ok(rows_affected)
+    }
+}
@@ -0,0 +1,103 @@
+use deadpool_postgres::Client;
+use serde::Serialize;
+use tokio_postgres::NoTls;
+
+use crate::http;
+
+#[derive(Debug, Serialize)]
+pub struct Product {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub image: Option<String>,
+}
+
+impl From<http::models::CreateProductRequest> for Product {
+    fn from(value: http::models::CreateProductRequest) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4(),
+            name: value.name,
+            description: value.description,
+            image: value.image,
+        }
+    }
+}
+
+impl Product {
+    pub fn parse_row(row: &tokio_postgres::Row) -> Product {
+        Product {
+            id: row.get("id"),
+            name: row.get("name"),
+            description: row.get("description"),
+            image: row.get("image"),
+        }
+    }
+
+    pub async fn insert(&self, client: &Client<NoTls>) -> Result<(), tokio_postgres::Error> {
+        let stmt = "INSERT into products(id,name,description,image) VALUES($1,$2,$3,$4)";
+        client
+            .execute(
+                stmt,
+                &[&self.id, &self.name, &self.description, &self.image],
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_by_id(
+        product_id: &uuid::Uuid,
+        client: &Client<NoTls>,
+    ) -> Result<Option<Product>, tokio_postgres::Error> {
+        let stmt = "SELECT * FROM products WHERE id = $1";
+        let rows = client.query(stmt, &[product_id]).await?;
+
+        Ok(rows.first().map(Product::parse_row))
+    }
+
+    pub async fn get_existing_ids(
+        product_ids: &[uuid::Uuid],
+        client: &Client<NoTls>,
+    ) -> Result<Vec<uuid::Uuid>, tokio_postgres::Error> {
+        let stmt = "SELECT id FROM products WHERE id = ANY($1)";
+        let rows = client.query(stmt, &[&product_ids]).await?;
+
+        Ok(rows.iter().map(|row| row.get("id")).collect())
+    }
+
+    pub async fn update(&self, client: &Client<NoTls>) -> Result<u64, tokio_postgres::Error> {
+        let stmt = "UPDATE products SET name = $1, description = $2, image = $3 WHERE id = $4";
+        let rows_affected = client
+            .execute(
+                stmt,
+                &[&self.name, &self.description, &self.image, &self.id],
+            )
+            .await?;
+
+        Ok(rows_affected)
+    }
+
+    pub async fn delete(
+        product_id: &uuid::Uuid,
+        client: &Client<NoTls>,
+    ) -> Result<u64, tokio_postgres::Error> {
+        let stmt = "DELETE FROM products WHERE id = $1";
+        let rows_affected = client.execute(stmt, &[product_id]).await?;
+
+        Ok(rows_affected)
+    }
+
+    pub async fn get_paginated(
+        limit: i64,
+        offset: i64,
+        client: &Client<NoTls>,
+    ) -> Result<Vec<Product>, tokio_postgres::Error> {
+        let stmt = "SELECT * FROM products ORDER BY name, id LIMIT $1 OFFSET $2";
+
+        Ok(client
+            .query(stmt, &[&limit, &offset])
+            .await?
+            .iter()
+            .map(Product::parse_row)
+            .collect())
+    }
+}
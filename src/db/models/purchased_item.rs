@@ -0,0 +1,62 @@
+use deadpool_postgres::Client;
+use serde::Serialize;
+use tokio_postgres::NoTls;
+
+use crate::units::{ProductUnit, Quantity};
+
+#[derive(Debug, Serialize)]
+pub struct PurchasedItem {
+    pub id: uuid::Uuid,
+    pub product_id: uuid::Uuid,
+    pub product_name: String,
+    pub group_id: uuid::Uuid,
+    pub list_id: uuid::Uuid,
+    pub unit: ProductUnit,
+    pub quantity: Quantity,
+    pub note: Option<String>,
+    pub purchased_by: Option<uuid::Uuid>,
+    pub purchased_at: chrono::DateTime<chrono::Utc>,
+    pub cleared_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl PurchasedItem {
+    pub fn parse_row(row: &tokio_postgres::Row) -> PurchasedItem {
+        PurchasedItem {
+            id: row.get("id"),
+            product_id: row.get("product_id"),
+            product_name: row.get("product_name"),
+            group_id: row.get("group_id"),
+            list_id: row.get("list_id"),
+            unit: row.get("unit"),
+            quantity: row.get("quantity"),
+            note: row.get("note"),
+            purchased_by: row.get("purchased_by"),
+            purchased_at: row.get("purchased_at"),
+            cleared_at: row.get("cleared_at"),
+        }
+    }
+
+    pub async fn get_by_list(
+        group_id: &uuid::Uuid,
+        list_id: &uuid::Uuid,
+        limit: i64,
+        offset: i64,
+        client: &Client<NoTls>,
+    ) -> Result<Vec<PurchasedItem>, tokio_postgres::Error> {
+        let stmt = "SELECT pi.id, pi.product_id, p.name AS product_name, pi.group_id, pi.list_id,
+                pi.unit, pi.quantity, pi.note,
+                pi.purchased_by, pi.purchased_at, pi.cleared_at
+            FROM purchased_items pi
+            JOIN products p ON p.id = pi.product_id
+            WHERE pi.group_id = $1 AND pi.list_id = $2
+            ORDER BY pi.purchased_at DESC, pi.id
+            LIMIT $3 OFFSET $4";
+
+        Ok(client
+            .query(stmt, &[group_id, list_id, &limit, &offset])
+            .await?
+            .iter()
+            .map(PurchasedItem::parse_row)
+            .collect())
+    }
+}
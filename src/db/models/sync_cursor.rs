@@ -0,0 +1,111 @@
+use deadpool_postgres::Client;
+use tokio_postgres::NoTls;
+
+use crate::messages::websocket::SyncCheckpoint;
+
+// Last positions in messages, group_messages and events that were delivered to a device of
+// a user.
+#[derive(Debug, Clone, Copy)]
+pub struct SyncCursor {
+    pub direct_sequence: i32,
+    pub group_sequence: i32,
+    pub event_sequence: i32,
+}
+
+impl SyncCursor {
+    pub fn parse_row(row: &tokio_postgres::Row) -> SyncCursor {
+        SyncCursor {
+            direct_sequence: row.get("direct_sequence"),
+            group_sequence: row.get("group_sequence"),
+            event_sequence: row.get("event_sequence"),
+        }
+    }
+
+    pub async fn get(
+        user_id: &uuid::Uuid,
+        device_id: &str,
+        client: &Client<NoTls>,
+    ) -> Result<Option<SyncCursor>, tokio_postgres::Error> {
+        let stmt = "SELECT * FROM user_sync_cursors WHERE user_id = $1 AND device_id = $2";
+        let rows = client.query(stmt, &[user_id, &device_id]).await?;
+
+        Ok(rows.first().map(SyncCursor::parse_row))
+    }
+
+    // Rows are only written after they were broadcast, so everything up to these positions was
+    // already sent to the sessions it belongs to.
+    pub async fn get_latest(client: &Client<NoTls>) -> Result<SyncCursor, tokio_postgres::Error> {
+        let stmt = "SELECT
+                (SELECT COALESCE(MAX(sequence), 0) FROM messages) AS direct_sequence,
+                (SELECT COALESCE(MAX(sequence), 0) FROM group_messages) AS group_sequence,
+                (SELECT COALESCE(MAX(sequence), 0) FROM events) AS event_sequence";
+        let row = client.query_one(stmt, &[]).await?;
+
+        Ok(SyncCursor::parse_row(&row))
+    }
+
+    // Never moves back, another device session may have acknowledged a later checkpoint.
+    pub async fn advance(
+        &self,
+        user_id: &uuid::Uuid,
+        device_id: &str,
+        client: &Client<NoTls>,
+    ) -> Result<(), tokio_postgres::Error> {
+        let stmt =
+            "INSERT into user_sync_cursors(user_id,device_id,direct_sequence,group_sequence,event_sequence)
+            VALUES($1,$2,$3,$4,$5)
+            ON CONFLICT (user_id, device_id) DO UPDATE SET
+                direct_sequence = GREATEST(user_sync_cursors.direct_sequence, EXCLUDED.direct_sequence),
+                group_sequence = GREATEST(user_sync_cursors.group_sequence, EXCLUDED.group_sequence),
+                event_sequence = GREATEST(user_sync_cursors.event_sequence, EXCLUDED.event_sequence),
+                updated_at = CURRENT_TIMESTAMP";
+        client
+            .execute(
+                stmt,
+                &[
+                    user_id,
+                    &device_id,
+                    &self.direct_sequence,
+                    &self.group_sequence,
+                    &self.event_sequence,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn advance_to_latest(
+        user_id: &uuid::Uuid,
+        device_id: &str,
+        client: &Client<NoTls>,
+    ) -> Result<(), tokio_postgres::Error> {
+        let stmt =
+            "INSERT into user_sync_cursors(user_id,device_id,direct_sequence,group_sequence,event_sequence)
+            VALUES(
+                $1,
+                $2,
+                (SELECT COALESCE(MAX(sequence), 0) FROM messages),
+                (SELECT COALESCE(MAX(sequence), 0) FROM group_messages),
+                (SELECT COALESCE(MAX(sequence), 0) FROM events)
+            )
+            ON CONFLICT (user_id, device_id) DO UPDATE SET
+                direct_sequence = EXCLUDED.direct_sequence,
+                group_sequence = EXCLUDED.group_sequence,
+                event_sequence = EXCLUDED.event_sequence,
+                updated_at = CURRENT_TIMESTAMP";
+        client.execute(stmt, &[user_id, &device_id]).await?;
+
+        Ok(())
+    }
+}
+
+impl From<SyncCheckpoint> for SyncCursor {
+    fn from(value: SyncCheckpoint) -> Self {
+        SyncCursor {
+            direct_sequence: value.direct_sequence,
+            group_sequence: value.group_sequence,
+            event_sequence: value.event_sequence,
+        }
+    }
+}
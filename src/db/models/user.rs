@@ -4,6 +4,7 @@ use tokio_postgres::NoTls;
 use uuid::Uuid;
 
 use super::Group;
+use crate::permissions::GroupRole;
 
 #[derive(Debug, Serialize)]
 pub struct User {
@@ -83,14 +84,55 @@ impl User {
         Ok(rows.first().map(User::parse_row))
     }
 
-    pub async fn get_group_ids_of_user(
+    pub async fn get_group_role(
         user_id: &uuid::Uuid,
+        group_id: &uuid::Uuid,
         client: &Client<NoTls>,
-    ) -> Result<Vec<uuid::Uuid>, tokio_postgres::Error> {
-        let stmt = "SELECT group_id FROM users_groups WHERE user_id = $1";
+    ) -> Result<Option<GroupRole>, tokio_postgres::Error> {
+        let stmt = "SELECT role FROM users_groups WHERE user_id = $1 AND group_id = $2";
+        let rows = client.query(stmt, &[user_id, group_id]).await?;
+
+        Ok(rows.first().map(|row| row.get("role")))
+    }
+
+    pub async fn get_group_roles_of_user(
+        user_id: &uuid::Uuid,
+        client: &Client<NoTls>,
+    ) -> Result<Vec<(uuid::Uuid, GroupRole)>, tokio_postgres::Error> {
+        let stmt = "SELECT group_id, role FROM users_groups WHERE user_id = $1";
         let rows = client.query(stmt, &[user_id]).await?;
 
-        Ok(rows.iter().map(|row| row.get("group_id")).collect())
+        Ok(rows
+            .iter()
+            .map(|row| (row.get("group_id"), row.get("role")))
+            .collect())
+    }
+
+    pub async fn set_last_seen(
+        user_id: &uuid::Uuid,
+        last_seen_at: &chrono::DateTime<chrono::Utc>,
+        client: &Client<NoTls>,
+    ) -> Result<u64, tokio_postgres::Error> {
+        let stmt = "UPDATE users SET last_seen_at = $2 WHERE id = $1";
+        let rows_affected = client.execute(stmt, &[user_id, last_seen_at]).await?;
+
+        Ok(rows_affected)
+    }
+
+    pub async fn get_last_seen_of_group(
+        group_id: &uuid::Uuid,
+        client: &Client<NoTls>,
+    ) -> Result<Vec<(uuid::Uuid, Option<chrono::DateTime<chrono::Utc>>)>, tokio_postgres::Error>
+    {
+        let stmt = "SELECT users.id, users.last_seen_at FROM users
+            JOIN users_groups ON users_groups.user_id = users.id
+            WHERE users_groups.group_id = $1";
+        let rows = client.query(stmt, &[group_id]).await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| (row.get("id"), row.get("last_seen_at")))
+            .collect())
     }
 
     pub async fn get_unhandled_groups_requests(
@@ -99,16 +141,19 @@ impl User {
     ) -> Result<Vec<(User, Group)>, tokio_postgres::Error> {
         let stmt = "
         SELECT 
-            u.id AS user_id, u.nickname, u.name, u.surname, u.email, u.image, u.password,
-            g.id AS group_id, g.name, g.created_by_user
+            u.id AS user_id, u.nickname, u.name AS user_name, u.surname, u.email, u.image, u.password,
+            g.id AS group_id, g.name AS group_name, g.created_by_user
         FROM 
             user_group_join_requests ugjr
         JOIN 
             users u ON ugjr.user_id = u.id
         JOIN 
             groups g ON ugjr.group_id = g.id
+        JOIN 
+            users_groups ug ON ug.group_id = g.id
         WHERE 
-            g.created_by_user = $1
+            ug.user_id = $1
+            AND ug.role IN ('owner', 'admin')
             AND ugjr.approved = 'unhandled'
     ";
         let rows = client.query(stmt, &[owner_id]).await?;
@@ -118,7 +163,7 @@ impl User {
             let user = User {
                 id: row.get("user_id"),
                 nickname: row.get("nickname"),
-                name: row.get("name"),
+                name: row.get("user_name"),
                 surname: row.get("surname"),
                 email: row.get("email"),
                 image: row.get("image"),
@@ -127,7 +172,7 @@ impl User {
 
             let group = Group {
                 id: row.get("group_id"),
-                name: row.get("name"),
+                name: row.get("group_name"),
                 created_by_user: row.get("created_by_user"),
             };
 
@@ -12,6 +12,7 @@ pub enum HttpError {
     NotFound,
     ServerError(String),
     Unauthorized,
+    Forbidden,
 }
 
 impl fmt::Display for HttpError {
@@ -19,6 +20,7 @@ impl fmt::Display for HttpError {
         match self {
             HttpError::BadRequest(message) => write!(f, "Bad Request: {}", message),
             HttpError::Unauthorized => write!(f, "Unauthorized"),
+            HttpError::Forbidden => write!(f, "Forbidden"),
             HttpError::NotFound => write!(f, "Not Found"),
 
             HttpError::ServerError(message) => write!(f, "Internal Server Error: {}", message),
@@ -39,6 +41,7 @@ impl error::ResponseError for HttpError {
             HttpError::BadRequest(_) => StatusCode::BAD_REQUEST,
             HttpError::NotFound => StatusCode::NOT_FOUND,
             HttpError::Unauthorized => StatusCode::UNAUTHORIZED,
+            HttpError::Forbidden => StatusCode::FORBIDDEN,
         }
     }
 }
@@ -83,3 +86,9 @@ impl From<tokio_postgres::Error> for HttpError {
         HttpError::ServerError(value.to_string())
     }
 }
+
+impl From<crate::mailer::MailerError> for HttpError {
+    fn from(value: crate::mailer::MailerError) -> HttpError {
+        HttpError::ServerError(value.to_string())
+    }
+}
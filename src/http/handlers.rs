@@ -1,13 +1,25 @@
+mod chat;
 mod group;
+mod invite;
+mod list;
+mod product;
 mod user;
 
 use super::jwt::{decode_jwt, Claims};
 use actix_web::Result;
+use deadpool_postgres::Client;
+use tokio_postgres::NoTls;
 
+pub use chat::chat_routes;
 pub use group::group_routes;
-pub use user::user_routes;
+pub use invite::invite_routes;
+pub use list::list_routes;
+pub use product::product_routes;
+pub use user::{user_routes, WebsocketConfig};
 
+use crate::db;
 use crate::http::error::HttpError;
+use crate::permissions::{GroupRole, Permission};
 
 fn get_auth_claims(req: &actix_web::HttpRequest) -> Result<Claims, HttpError> {
     let token = req
@@ -18,3 +30,21 @@ fn get_auth_claims(req: &actix_web::HttpRequest) -> Result<Claims, HttpError> {
         .ok_or(HttpError::Unauthorized)?;
     Ok(decode_jwt(token)?)
 }
+
+async fn authorize_group(
+    req: &actix_web::HttpRequest,
+    group_id: &uuid::Uuid,
+    permission: Permission,
+    client: &Client<NoTls>,
+) -> Result<(Claims, GroupRole), HttpError> {
+    let claims = get_auth_claims(req)?;
+    let role = db::models::User::get_group_role(&claims.sub, group_id, client)
+        .await?
+        .ok_or(HttpError::Unauthorized)?;
+
+    if !role.allows(permission) {
+        return Err(HttpError::Forbidden);
+    }
+
+    Ok((claims, role))
+}
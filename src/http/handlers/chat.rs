@@ -0,0 +1,228 @@
+use crate::constants;
+use crate::db;
+use crate::db::models::chat_message::{DirectChatMessage, GroupChatMessage};
+use crate::http::error::HttpError;
+use crate::http::models;
+use crate::messages::websocket::{DirectChatMessageResponse, GroupChatMessageResponse};
+use crate::messages::workers::DatabaseWorkerRequest;
+use crate::permissions::Permission;
+use actix_web::{web, HttpResponse, Result};
+use deadpool_postgres::Pool;
+use tokio::sync::{mpsc, oneshot};
+use tokio_postgres::NoTls;
+use validator::Validate;
+
+async fn get_direct_chat_history(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid,)>,
+    query: web::Query<models::ChatHistoryQuery>,
+    db_pool: web::Data<Pool<NoTls>>,
+    database_sender: web::Data<mpsc::UnboundedSender<DatabaseWorkerRequest>>,
+) -> Result<HttpResponse, HttpError> {
+    query.validate()?;
+    let peer_id = path.into_inner().0;
+    let claims = super::get_auth_claims(&req)?;
+    let limit = query.limit.unwrap_or(constants::DEFAULT_PAGE_LIMIT);
+
+    // Pending messages are read before the table, so a flush in between only causes duplicates.
+    let (reply_sender, reply_receiver) = oneshot::channel();
+    database_sender
+        .send(DatabaseWorkerRequest::PendingDirectChat(
+            claims.sub,
+            peer_id,
+            reply_sender,
+        ))
+        .expect("Failed to send message to database worker");
+    let pending = reply_receiver.await.map_err(|_| {
+        HttpError::ServerError("Database worker dropped pending chat reply".to_string())
+    })?;
+
+    let client = db_pool.get().await?;
+    let stored = DirectChatMessage::get_conversation(
+        &client,
+        &claims.sub,
+        &peer_id,
+        query.before,
+        limit + 1,
+    )
+    .await?
+    .into_iter()
+    .map(DirectChatMessageResponse::from);
+
+    let page = build_page(
+        pending.into_iter().chain(stored),
+        query.before,
+        limit,
+        |message| (message.id, message.sequence),
+    );
+
+    Ok(HttpResponse::Ok().json(serde_json::to_string(&page)?))
+}
+
+async fn get_group_chat_history(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid,)>,
+    query: web::Query<models::ChatHistoryQuery>,
+    db_pool: web::Data<Pool<NoTls>>,
+    database_sender: web::Data<mpsc::UnboundedSender<DatabaseWorkerRequest>>,
+) -> Result<HttpResponse, HttpError> {
+    query.validate()?;
+    let group_id = path.into_inner().0;
+    let limit = query.limit.unwrap_or(constants::DEFAULT_PAGE_LIMIT);
+
+    let client = db_pool.get().await?;
+    super::authorize_group(&req, &group_id, Permission::ViewGroup, &client).await?;
+
+    let (reply_sender, reply_receiver) = oneshot::channel();
+    database_sender
+        .send(DatabaseWorkerRequest::PendingGroupChat(
+            group_id,
+            reply_sender,
+        ))
+        .expect("Failed to send message to database worker");
+    let pending = reply_receiver.await.map_err(|_| {
+        HttpError::ServerError("Database worker dropped pending chat reply".to_string())
+    })?;
+
+    let stored = GroupChatMessage::get_page(&client, &group_id, query.before, limit + 1)
+        .await?
+        .into_iter()
+        .map(GroupChatMessageResponse::from);
+
+    let page = build_page(
+        pending.into_iter().chain(stored),
+        query.before,
+        limit,
+        |message| (message.id, message.sequence),
+    );
+
+    Ok(HttpResponse::Ok().json(serde_json::to_string(&page)?))
+}
+
+// Conversations ordered by their latest message, groups without messages last.
+async fn get_inbox(
+    req: actix_web::HttpRequest,
+    db_pool: web::Data<Pool<NoTls>>,
+) -> Result<HttpResponse, HttpError> {
+    let claims = super::get_auth_claims(&req)?;
+
+    // Counts and last messages come from the database only, so they can lag behind by up to one
+    // flush interval of the database worker.
+    let client = db_pool.get().await?;
+    let mut inbox = db::models::InboxEntry::get_direct(&claims.sub, &client).await?;
+    inbox.extend(db::models::InboxEntry::get_groups(&claims.sub, &client).await?);
+    inbox.sort_by_key(|entry| {
+        std::cmp::Reverse(
+            entry
+                .last_message
+                .as_ref()
+                .map(|message| message.created_at),
+        )
+    });
+
+    let inbox = inbox
+        .into_iter()
+        .map(models::InboxEntry::from)
+        .collect::<Vec<models::InboxEntry>>();
+
+    Ok(HttpResponse::Ok().json(serde_json::to_string(&inbox)?))
+}
+
+// Merges pending and stored messages into a page ordered by sequence, newest first. Stored
+// messages are loaded with one extra row, so a longer result means there is another page.
+fn build_page<T, F>(
+    messages: impl Iterator<Item = T>,
+    before: Option<i32>,
+    limit: i64,
+    key: F,
+) -> models::ChatHistoryPage<T>
+where
+    F: Fn(&T) -> (uuid::Uuid, i32),
+{
+    let mut messages = messages
+        .filter(|message| before.is_none_or(|before| key(message).1 < before))
+        .collect::<Vec<T>>();
+    messages.sort_by_key(|message| std::cmp::Reverse(key(message).1));
+    messages.dedup_by_key(|message| key(message).0);
+
+    let has_more = messages.len() as i64 > limit;
+    messages.truncate(limit as usize);
+    let next_cursor = if has_more {
+        messages.last().map(|message| key(message).1)
+    } else {
+        None
+    };
+
+    models::ChatHistoryPage {
+        messages,
+        next_cursor,
+    }
+}
+
+pub fn chat_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/chat/inbox", web::get().to(get_inbox))
+        .route(
+            "/chat/direct/{peer_id}",
+            web::get().to(get_direct_chat_history),
+        )
+        .route(
+            "/chat/group/{group_id}",
+            web::get().to(get_group_chat_history),
+        );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(sequence: i32) -> (uuid::Uuid, i32) {
+        (uuid::Uuid::new_v4(), sequence)
+    }
+
+    fn sequences(page: &models::ChatHistoryPage<(uuid::Uuid, i32)>) -> Vec<i32> {
+        page.messages.iter().map(|message| message.1).collect()
+    }
+
+    #[test]
+    fn merges_pending_and_stored_messages_by_sequence() {
+        let pending = vec![message(5), message(3)];
+        let stored = vec![message(4), message(2), message(1)];
+
+        let page = build_page(pending.into_iter().chain(stored), None, 10, |m| *m);
+
+        assert_eq!(sequences(&page), vec![5, 4, 3, 2, 1]);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn drops_messages_that_were_flushed_while_reading() {
+        let flushed = message(3);
+        let pending = vec![message(4), flushed];
+        let stored = vec![flushed, message(2)];
+
+        let page = build_page(pending.into_iter().chain(stored), None, 10, |m| *m);
+
+        assert_eq!(sequences(&page), vec![4, 3, 2]);
+    }
+
+    #[test]
+    fn sets_the_cursor_when_more_messages_exist() {
+        let stored = (1..=4).rev().map(message).collect::<Vec<_>>();
+
+        let page = build_page(stored.into_iter(), None, 3, |m| *m);
+
+        assert_eq!(sequences(&page), vec![4, 3, 2]);
+        assert_eq!(page.next_cursor, Some(2));
+    }
+
+    #[test]
+    fn skips_pending_messages_at_or_after_the_cursor() {
+        let pending = vec![message(7), message(5)];
+        let stored = vec![message(4), message(3)];
+
+        let page = build_page(pending.into_iter().chain(stored), Some(5), 10, |m| *m);
+
+        assert_eq!(sequences(&page), vec![4, 3]);
+        assert_eq!(page.next_cursor, None);
+    }
+}
@@ -1,9 +1,16 @@
+use crate::constants;
+use crate::db::models::group::ApproveJoinResolution;
 use crate::http::models;
-use crate::messages::websocket::{ApproveJoin, JoinGroupRequest};
+use crate::messages::websocket::{
+    ApproveJoin, GroupUpdate, GroupUpdateMessage, JoinGroupRequest, MemberRemovedMessage,
+    PresenceStatus, WebsocketMessageRequest,
+};
+use crate::permissions::{GroupRole, Permission};
 use crate::{db, messages::workers::WorkerMessageRequest};
 use actix_web::{web, HttpResponse, Result};
+use chrono::{Duration, Utc};
 use deadpool_postgres::Pool;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
 use tokio_postgres::NoTls;
 use validator::Validate;
 
@@ -32,22 +39,241 @@ async fn create_group(
     Ok(HttpResponse::Ok().json(serde_json::to_string(&create_group_response)?))
 }
 
-async fn get_group_users(
+async fn rename_group(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid,)>,
+    rename_group_request: web::Json<models::RenameGroupRequest>,
+    db_pool: web::Data<Pool<NoTls>>,
+    mpsc_sender: web::Data<mpsc::UnboundedSender<WorkerMessageRequest>>,
+) -> Result<HttpResponse, HttpError> {
+    rename_group_request.validate()?;
+    let group_id = path.into_inner().0;
+    let name = rename_group_request.into_inner().name;
+
+    let client = db_pool.get().await?;
+    let (claims, _) =
+        super::authorize_group(&req, &group_id, Permission::ManageGroup, &client).await?;
+    let mut group = db::models::Group::get_by_id(&group_id, &client)
+        .await?
+        .ok_or(HttpError::NotFound)?;
+
+    db::models::Group::rename(&group_id, &name, &client).await?;
+
+    mpsc_sender
+        .send(WorkerMessageRequest::GroupUpdate(GroupUpdateMessage {
+            sender_id: claims.sub,
+            group_id,
+            update: GroupUpdate::Renamed { name: name.clone() },
+        }))
+        .expect("Failed to send message to websocket worker");
+
+    group.name = name;
+    Ok(HttpResponse::Ok().json(serde_json::to_string(&models::Group::from(group))?))
+}
+
+async fn transfer_group_ownership(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid,)>,
+    transfer_request: web::Json<models::TransferGroupOwnershipRequest>,
+    db_pool: web::Data<Pool<NoTls>>,
+    mpsc_sender: web::Data<mpsc::UnboundedSender<WorkerMessageRequest>>,
+) -> Result<HttpResponse, HttpError> {
+    let group_id = path.into_inner().0;
+    let new_owner_id = transfer_request.into_inner().new_owner_id;
+
+    let mut client = db_pool.get().await?;
+    let (claims, _) =
+        super::authorize_group(&req, &group_id, Permission::OwnGroup, &client).await?;
+    let mut group = db::models::Group::get_by_id(&group_id, &client)
+        .await?
+        .ok_or(HttpError::NotFound)?;
+
+    if new_owner_id == claims.sub {
+        return Err(HttpError::BadRequest(
+            "User is already owner of the group".to_string(),
+        ));
+    }
+
+    if db::models::Group::transfer_ownership(&group_id, &claims.sub, &new_owner_id, &mut client)
+        .await?
+        == 0
+    {
+        return Err(HttpError::BadRequest(
+            "New owner is not member of the group".to_string(),
+        ));
+    }
+
+    mpsc_sender
+        .send(WorkerMessageRequest::GroupUpdate(GroupUpdateMessage {
+            sender_id: claims.sub,
+            group_id,
+            update: GroupUpdate::OwnerChanged {
+                owner_id: new_owner_id,
+            },
+        }))
+        .expect("Failed to send message to websocket worker");
+
+    group.created_by_user = new_owner_id;
+    Ok(HttpResponse::Ok().json(serde_json::to_string(&models::Group::from(group))?))
+}
+
+async fn delete_group(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid,)>,
+    db_pool: web::Data<Pool<NoTls>>,
+    mpsc_sender: web::Data<mpsc::UnboundedSender<WorkerMessageRequest>>,
+) -> Result<HttpResponse, HttpError> {
+    let group_id = path.into_inner().0;
+
+    let client = db_pool.get().await?;
+    let (claims, _) =
+        super::authorize_group(&req, &group_id, Permission::OwnGroup, &client).await?;
+
+    db::models::Group::delete(&group_id, &client).await?;
+
+    mpsc_sender
+        .send(WorkerMessageRequest::GroupUpdate(GroupUpdateMessage {
+            sender_id: claims.sub,
+            group_id,
+            update: GroupUpdate::Deleted,
+        }))
+        .expect("Failed to send message to websocket worker");
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+async fn leave_group(
     req: actix_web::HttpRequest,
     path: web::Path<(uuid::Uuid,)>,
     db_pool: web::Data<Pool<NoTls>>,
+    mpsc_sender: web::Data<mpsc::UnboundedSender<WorkerMessageRequest>>,
 ) -> Result<HttpResponse, HttpError> {
     let group_id = path.into_inner().0;
     let claims = super::get_auth_claims(&req)?;
 
     let client = db_pool.get().await?;
+    let role = db::models::User::get_group_role(&claims.sub, &group_id, &client)
+        .await?
+        .ok_or(HttpError::NotFound)?;
 
-    let user_group_ids = db::models::User::get_group_ids_of_user(&claims.sub, &client).await?;
+    if role == GroupRole::Owner {
+        return Err(HttpError::BadRequest(
+            "Owner has to transfer the group before leaving".to_string(),
+        ));
+    }
 
-    if !user_group_ids.contains(&group_id) {
-        return Err(HttpError::Unauthorized);
+    if db::models::Group::remove_member(&group_id, &claims.sub, &client).await? == 0 {
+        return Err(HttpError::BadRequest(
+            "User is not member of the group".to_string(),
+        ));
     }
 
+    mpsc_sender
+        .send(WorkerMessageRequest::MemberRemoved(MemberRemovedMessage {
+            sender_id: claims.sub,
+            group_id,
+            user_id: claims.sub,
+        }))
+        .expect("Failed to send message to websocket worker");
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+async fn remove_group_member(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    db_pool: web::Data<Pool<NoTls>>,
+    mpsc_sender: web::Data<mpsc::UnboundedSender<WorkerMessageRequest>>,
+) -> Result<HttpResponse, HttpError> {
+    let (group_id, user_id) = path.into_inner();
+
+    let client = db_pool.get().await?;
+    let (claims, role) =
+        super::authorize_group(&req, &group_id, Permission::ManageMembers, &client).await?;
+
+    if user_id == claims.sub {
+        return Err(HttpError::BadRequest(
+            "Use leave to remove yourself from the group".to_string(),
+        ));
+    }
+
+    let member_role = db::models::User::get_group_role(&user_id, &group_id, &client)
+        .await?
+        .ok_or(HttpError::NotFound)?;
+
+    if !role.outranks(member_role) {
+        return Err(HttpError::Forbidden);
+    }
+
+    if db::models::Group::remove_member(&group_id, &user_id, &client).await? == 0 {
+        return Err(HttpError::NotFound);
+    }
+
+    mpsc_sender
+        .send(WorkerMessageRequest::MemberRemoved(MemberRemovedMessage {
+            sender_id: claims.sub,
+            group_id,
+            user_id,
+        }))
+        .expect("Failed to send message to websocket worker");
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+async fn set_group_member_role(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    set_role_request: web::Json<models::SetMemberRoleRequest>,
+    db_pool: web::Data<Pool<NoTls>>,
+    mpsc_sender: web::Data<mpsc::UnboundedSender<WorkerMessageRequest>>,
+) -> Result<HttpResponse, HttpError> {
+    let (group_id, user_id) = path.into_inner();
+    let new_role = set_role_request.into_inner().role;
+
+    let client = db_pool.get().await?;
+    let (claims, role) =
+        super::authorize_group(&req, &group_id, Permission::ManageMembers, &client).await?;
+
+    if new_role == GroupRole::Owner {
+        return Err(HttpError::BadRequest(
+            "Ownership has to be transferred instead".to_string(),
+        ));
+    }
+
+    let member_role = db::models::User::get_group_role(&user_id, &group_id, &client)
+        .await?
+        .ok_or(HttpError::NotFound)?;
+
+    if !role.outranks(member_role) || !role.outranks(new_role) {
+        return Err(HttpError::Forbidden);
+    }
+
+    db::models::Group::set_member_role(&group_id, &user_id, new_role, &client).await?;
+
+    mpsc_sender
+        .send(WorkerMessageRequest::GroupUpdate(GroupUpdateMessage {
+            sender_id: claims.sub,
+            group_id,
+            update: GroupUpdate::MemberRoleChanged {
+                user_id,
+                role: new_role,
+            },
+        }))
+        .expect("Failed to send message to websocket worker");
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+async fn get_group_users(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid,)>,
+    db_pool: web::Data<Pool<NoTls>>,
+) -> Result<HttpResponse, HttpError> {
+    let group_id = path.into_inner().0;
+
+    let client = db_pool.get().await?;
+    super::authorize_group(&req, &group_id, Permission::ViewGroup, &client).await?;
+
     let users = db::models::Group::get_users(&group_id, &client)
         .await?
         .into_iter()
@@ -60,6 +286,76 @@ async fn get_group_users(
     Ok(HttpResponse::Ok().json(users))
 }
 
+async fn get_group_presence(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid,)>,
+    db_pool: web::Data<Pool<NoTls>>,
+    mpsc_sender: web::Data<mpsc::UnboundedSender<WorkerMessageRequest>>,
+) -> Result<HttpResponse, HttpError> {
+    let group_id = path.into_inner().0;
+
+    let client = db_pool.get().await?;
+    super::authorize_group(&req, &group_id, Permission::ViewGroup, &client).await?;
+
+    let members = db::models::User::get_last_seen_of_group(&group_id, &client).await?;
+
+    let (reply_sender, reply_receiver) = oneshot::channel();
+    mpsc_sender
+        .send(WorkerMessageRequest::OnlineUsers(
+            members.iter().map(|(user_id, _)| *user_id).collect(),
+            reply_sender,
+        ))
+        .expect(constants::FAILED_TO_SEND_MESSAGE_TO_STATE_WORKER);
+    let online = reply_receiver.await.map_err(|_| {
+        HttpError::ServerError("Message worker dropped online users reply".to_string())
+    })?;
+
+    let presence = members
+        .into_iter()
+        .map(|(user_id, last_seen)| {
+            let status = if online.contains(&user_id) {
+                PresenceStatus::Online
+            } else {
+                PresenceStatus::Offline
+            };
+            models::MemberPresence {
+                user_id,
+                status,
+                last_seen,
+            }
+        })
+        .collect::<Vec<models::MemberPresence>>();
+
+    Ok(HttpResponse::Ok().json(serde_json::to_string(&presence)?))
+}
+
+async fn get_group_items(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid,)>,
+    query: web::Query<models::GroupItemsQuery>,
+    db_pool: web::Data<Pool<NoTls>>,
+) -> Result<HttpResponse, HttpError> {
+    let group_id = path.into_inner().0;
+    let query = query.into_inner();
+
+    let client = db_pool.get().await?;
+    super::authorize_group(&req, &group_id, Permission::ViewGroup, &client).await?;
+
+    let items = db::models::Item::get_by_group(
+        &group_id,
+        query.list_id.as_ref(),
+        query.name.as_deref(),
+        query.sort.unwrap_or_default().into(),
+        &client,
+    )
+    .await?
+    .into_iter()
+    .map(models::Item::from)
+    .collect::<Vec<models::Item>>();
+
+    Ok(HttpResponse::Ok().json(serde_json::to_string(&items)?))
+}
+
 async fn create_join_group_request(
     req: actix_web::HttpRequest,
     path: web::Path<(uuid::Uuid,)>,
@@ -84,15 +380,49 @@ async fn create_join_group_request(
         ));
     }
 
+    if db::models::User::get_group_role(&claims.sub, &group_id, &client)
+        .await?
+        .is_some()
+    {
+        return Err(HttpError::BadRequest(
+            "User is already member of the group".to_string(),
+        ));
+    }
+
+    if let Some(join_request) =
+        db::models::Group::get_group_request(&group_id, &claims.sub, &client).await?
+    {
+        match join_request.approved {
+            ApproveJoinResolution::Unhandled => {
+                return Err(HttpError::BadRequest(
+                    "Join request is already pending".to_string(),
+                ));
+            }
+            ApproveJoinResolution::Unapproved => {
+                let cooldown_end = join_request.resolved_at.unwrap_or(join_request.created_at)
+                    + Duration::hours(constants::JOIN_REQUEST_COOLDOWN_HOURS);
+
+                if cooldown_end > Utc::now() {
+                    return Err(HttpError::BadRequest(format!(
+                        "Join request was rejected, it can be repeated after {}",
+                        cooldown_end
+                    )));
+                }
+            }
+            ApproveJoinResolution::Approved => {}
+        }
+    }
+
     db::models::Group::create_group_request(&group_id, &claims.sub, &client).await?;
 
     mpsc_sender
         .send(WorkerMessageRequest::WebsocketMessage(
-            crate::messages::websocket::WebsocketMessageRequest::JoinGroup(JoinGroupRequest {
+            WebsocketMessageRequest::JoinGroup(JoinGroupRequest {
                 sender_id: claims.sub,
                 group_owner_id: group.created_by_user,
                 group_id,
-            }),
+            })
+            .into(),
         ))
         .expect("Failed to send message to websocket worker");
 
@@ -106,20 +436,20 @@ async fn handle_join_request(
     mpsc_sender: web::Data<mpsc::UnboundedSender<WorkerMessageRequest>>,
 ) -> Result<HttpResponse, HttpError> {
     let approve_join = approve_join.into_inner();
-    let claims = super::get_auth_claims(&req)?;
-    let client = db_pool.get().await?;
-
-    let user_group_ids = db::models::User::get_group_ids_of_user(&claims.sub, &client).await?;
-
-    if !user_group_ids.contains(&approve_join.group_id) {
-        return Err(HttpError::BadRequest("Not owner of the group".to_string()));
-    }
+    let mut client = db_pool.get().await?;
+    let (claims, _) = super::authorize_group(
+        &req,
+        &approve_join.group_id,
+        Permission::ApproveJoins,
+        &client,
+    )
+    .await?;
 
     let affected_rows = db::models::Group::handle_group_request(
         &approve_join.group_id,
         &approve_join.candidate_id,
         approve_join.approved.into(),
-        &client,
+        &mut client,
     )
     .await?;
 
@@ -136,18 +466,68 @@ async fn handle_join_request(
         group_id: approve_join.group_id,
     };
 
-    mpsc_sender.send(WorkerMessageRequest::WebsocketMessage(
-        websocket_approve_join.into(),
-    ));
+    mpsc_sender
+        .send(WorkerMessageRequest::WebsocketMessage(
+            WebsocketMessageRequest::from(websocket_approve_join).into(),
+        ))
+        .expect("Failed to send message to websocket worker");
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+async fn cancel_join_group_request(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid,)>,
+    db_pool: web::Data<Pool<NoTls>>,
+) -> Result<HttpResponse, HttpError> {
+    let group_id = path.into_inner().0;
+    let claims = super::get_auth_claims(&req)?;
+    let client = db_pool.get().await?;
+
+    if db::models::Group::cancel_group_request(&group_id, &claims.sub, &client).await? == 0 {
+        return Err(HttpError::BadRequest(
+            "User has no pending join request to the group".to_string(),
+        ));
+    }
 
     Ok(HttpResponse::Ok().finish())
 }
 
 pub fn group_routes(cfg: &mut web::ServiceConfig) {
+    // Static segments are registered before `{group_id}` routes, actix matches in order and
+    // would otherwise try to parse them as a group id.
     cfg.route("/group", web::post().to(create_group))
-        .route("/group/user/{group_id}", web::get().to(get_group_users))
+        .route(
+            "/group/user-join-request",
+            web::put().to(handle_join_request),
+        )
         .route(
             "/group/user-join-request/{group_id}",
             web::post().to(create_join_group_request),
-        );
+        )
+        .route(
+            "/group/user-join-request/{group_id}",
+            web::delete().to(cancel_join_group_request),
+        )
+        .route("/group/{group_id}", web::put().to(rename_group))
+        .route("/group/{group_id}", web::delete().to(delete_group))
+        .route(
+            "/group/{group_id}/owner",
+            web::put().to(transfer_group_ownership),
+        )
+        .route("/group/user/{group_id}", web::get().to(get_group_users))
+        .route("/group/{group_id}/leave", web::post().to(leave_group))
+        .route(
+            "/group/{group_id}/presence",
+            web::get().to(get_group_presence),
+        )
+        .route(
+            "/group/{group_id}/user/{user_id}",
+            web::delete().to(remove_group_member),
+        )
+        .route(
+            "/group/{group_id}/user/{user_id}/role",
+            web::put().to(set_group_member_role),
+        )
+        .route("/group/{group_id}/items", web::get().to(get_group_items));
 }
@@ -0,0 +1,234 @@
+use crate::db;
+use crate::http::error::HttpError;
+use crate::http::models;
+use crate::mailer::{Mail, Mailer};
+use crate::messages::websocket::{GroupUpdate, GroupUpdateMessage};
+use crate::messages::workers::WorkerMessageRequest;
+use crate::permissions::{GroupRole, Permission};
+use actix_web::{web, HttpResponse, Result};
+use chrono::{Duration, Utc};
+use deadpool_postgres::Pool;
+use tokio::sync::mpsc;
+use tokio_postgres::NoTls;
+use validator::Validate;
+
+async fn create_invite(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid,)>,
+    create_invite_request: web::Json<models::CreateInviteRequest>,
+    db_pool: web::Data<Pool<NoTls>>,
+) -> Result<HttpResponse, HttpError> {
+    create_invite_request.validate()?;
+    let group_id = path.into_inner().0;
+    let create_invite_request = create_invite_request.into_inner();
+    let invite_role = create_invite_request.role.unwrap_or(GroupRole::Member);
+
+    let client = db_pool.get().await?;
+    let (claims, role) =
+        super::authorize_group(&req, &group_id, Permission::ManageMembers, &client).await?;
+
+    if invite_role == GroupRole::Owner {
+        return Err(HttpError::BadRequest(
+            "Invite can not grant ownership".to_string(),
+        ));
+    }
+
+    if !role.outranks(invite_role) {
+        return Err(HttpError::Forbidden);
+    }
+
+    let invite = db::models::GroupInvite::new(
+        group_id,
+        claims.sub,
+        invite_role,
+        create_invite_request.max_uses,
+        create_invite_request
+            .expires_in_hours
+            .map(|hours| Utc::now() + Duration::hours(hours)),
+        None,
+    );
+    invite.insert(&client).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::to_string(&models::GroupInvite::from(invite))?))
+}
+
+async fn create_email_invite(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid,)>,
+    create_invite_request: web::Json<models::CreateEmailInviteRequest>,
+    db_pool: web::Data<Pool<NoTls>>,
+    mailer: web::Data<Mailer>,
+) -> Result<HttpResponse, HttpError> {
+    create_invite_request.validate()?;
+    let group_id = path.into_inner().0;
+    let create_invite_request = create_invite_request.into_inner();
+    let invite_role = create_invite_request.role.unwrap_or(GroupRole::Member);
+
+    let client = db_pool.get().await?;
+    let (claims, role) =
+        super::authorize_group(&req, &group_id, Permission::ManageMembers, &client).await?;
+
+    if invite_role == GroupRole::Owner {
+        return Err(HttpError::BadRequest(
+            "Invite can not grant ownership".to_string(),
+        ));
+    }
+
+    if !role.outranks(invite_role) {
+        return Err(HttpError::Forbidden);
+    }
+
+    if let Some(user) =
+        db::models::User::get_by_email(&create_invite_request.email, &client).await?
+    {
+        if db::models::User::get_group_role(&user.id, &group_id, &client)
+            .await?
+            .is_some()
+        {
+            return Err(HttpError::BadRequest(
+                "User is already member of the group".to_string(),
+            ));
+        }
+    }
+
+    let group = db::models::Group::get_by_id(&group_id, &client)
+        .await?
+        .ok_or(HttpError::NotFound)?;
+
+    let invite = db::models::GroupInvite::new(
+        group_id,
+        claims.sub,
+        invite_role,
+        Some(1),
+        create_invite_request
+            .expires_in_hours
+            .map(|hours| Utc::now() + Duration::hours(hours)),
+        Some(create_invite_request.email.clone()),
+    );
+    let mail = Mail {
+        to: create_invite_request.email,
+        subject: format!("You are invited to join {}", group.name),
+        body: format!(
+            "You have been invited to join the group {}.\n\n\
+            Sign in or create an account, then redeem the invite code {} to join.",
+            group.name, invite.token
+        ),
+    };
+    // Committed before sending, so no connection is held while the mail goes out. An invite
+    // whose mail could not be sent is removed again.
+    invite.insert(&client).await?;
+    drop(client);
+    if let Err(err) = mailer.send(mail).await {
+        let client = db_pool.get().await?;
+        db::models::GroupInvite::delete(&invite.id, &client).await?;
+        return Err(err.into());
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::to_string(&models::GroupInvite::from(invite))?))
+}
+
+async fn get_invites(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid,)>,
+    db_pool: web::Data<Pool<NoTls>>,
+) -> Result<HttpResponse, HttpError> {
+    let group_id = path.into_inner().0;
+
+    let client = db_pool.get().await?;
+    super::authorize_group(&req, &group_id, Permission::ManageMembers, &client).await?;
+
+    let invites = db::models::GroupInvite::get_by_group(&group_id, &client)
+        .await?
+        .into_iter()
+        .map(models::GroupInvite::from)
+        .collect::<Vec<models::GroupInvite>>();
+
+    Ok(HttpResponse::Ok().json(serde_json::to_string(&invites)?))
+}
+
+async fn revoke_invite(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    db_pool: web::Data<Pool<NoTls>>,
+) -> Result<HttpResponse, HttpError> {
+    let (group_id, invite_id) = path.into_inner();
+
+    let client = db_pool.get().await?;
+    super::authorize_group(&req, &group_id, Permission::ManageMembers, &client).await?;
+
+    if db::models::GroupInvite::revoke(&invite_id, &group_id, &client).await? == 0 {
+        return Err(HttpError::NotFound);
+    }
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+async fn redeem_invite(
+    req: actix_web::HttpRequest,
+    path: web::Path<(String,)>,
+    db_pool: web::Data<Pool<NoTls>>,
+    mpsc_sender: web::Data<mpsc::UnboundedSender<WorkerMessageRequest>>,
+) -> Result<HttpResponse, HttpError> {
+    let token = path.into_inner().0;
+    let claims = super::get_auth_claims(&req)?;
+
+    let mut client = db_pool.get().await?;
+    let invite = db::models::GroupInvite::get_by_token(&token, &client)
+        .await?
+        .filter(|invite| !invite.revoked)
+        .ok_or(HttpError::NotFound)?;
+
+    if invite.is_expired() {
+        return Err(HttpError::BadRequest("Invite has expired".to_string()));
+    }
+
+    if invite.is_exhausted() {
+        return Err(HttpError::BadRequest("Invite has no uses left".to_string()));
+    }
+
+    if db::models::User::get_group_role(&claims.sub, &invite.group_id, &client)
+        .await?
+        .is_some()
+    {
+        return Err(HttpError::BadRequest(
+            "User is already member of the group".to_string(),
+        ));
+    }
+
+    if invite.redeem(&claims.sub, &mut client).await? == 0 {
+        return Err(HttpError::BadRequest(
+            "Invite is no longer valid".to_string(),
+        ));
+    }
+
+    let group = db::models::Group::get_by_id(&invite.group_id, &client)
+        .await?
+        .ok_or(HttpError::NotFound)?;
+
+    mpsc_sender
+        .send(WorkerMessageRequest::GroupUpdate(GroupUpdateMessage {
+            sender_id: claims.sub,
+            group_id: invite.group_id,
+            update: GroupUpdate::MemberJoined {
+                user_id: claims.sub,
+                role: invite.role,
+            },
+        }))
+        .expect("Failed to send message to websocket worker");
+
+    Ok(HttpResponse::Ok().json(serde_json::to_string(&models::Group::from(group))?))
+}
+
+pub fn invite_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/group/{group_id}/invites", web::post().to(create_invite))
+        .route("/group/{group_id}/invites", web::get().to(get_invites))
+        .route(
+            "/group/{group_id}/invites/email",
+            web::post().to(create_email_invite),
+        )
+        .route(
+            "/group/{group_id}/invites/{invite_id}",
+            web::delete().to(revoke_invite),
+        )
+        .route("/invites/{token}/redeem", web::post().to(redeem_invite));
+}
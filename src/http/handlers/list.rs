@@ -0,0 +1,160 @@
+use crate::constants;
+use crate::db;
+use crate::http::error::HttpError;
+use crate::http::models;
+use crate::permissions::Permission;
+use actix_web::{web, HttpResponse, Result};
+use deadpool_postgres::Pool;
+use tokio_postgres::NoTls;
+use validator::Validate;
+
+async fn create_list(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid,)>,
+    create_list_request: web::Json<models::CreateListRequest>,
+    db_pool: web::Data<Pool<NoTls>>,
+) -> Result<HttpResponse, HttpError> {
+    create_list_request.validate()?;
+    let group_id = path.into_inner().0;
+
+    let client = db_pool.get().await?;
+    super::authorize_group(&req, &group_id, Permission::EditItems, &client).await?;
+
+    let mut list = db::models::List::new(group_id, create_list_request.into_inner().name);
+    list.insert(&client).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::to_string(&models::List::from(list))?))
+}
+
+async fn get_lists(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid,)>,
+    query: web::Query<models::ListsQuery>,
+    db_pool: web::Data<Pool<NoTls>>,
+) -> Result<HttpResponse, HttpError> {
+    let group_id = path.into_inner().0;
+
+    let client = db_pool.get().await?;
+    super::authorize_group(&req, &group_id, Permission::ViewGroup, &client).await?;
+
+    let lists =
+        db::models::List::get_by_group(&group_id, query.include_archived.unwrap_or(false), &client)
+            .await?
+            .into_iter()
+            .map(models::List::from)
+            .collect::<Vec<models::List>>();
+
+    Ok(HttpResponse::Ok().json(serde_json::to_string(&lists)?))
+}
+
+async fn rename_list(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    rename_list_request: web::Json<models::RenameListRequest>,
+    db_pool: web::Data<Pool<NoTls>>,
+) -> Result<HttpResponse, HttpError> {
+    rename_list_request.validate()?;
+    let (group_id, list_id) = path.into_inner();
+
+    let client = db_pool.get().await?;
+    super::authorize_group(&req, &group_id, Permission::EditItems, &client).await?;
+
+    if db::models::List::rename(&list_id, &group_id, &rename_list_request.name, &client).await? == 0
+    {
+        return Err(HttpError::NotFound);
+    }
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+async fn reorder_lists(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid,)>,
+    reorder_lists_request: web::Json<models::ReorderListsRequest>,
+    db_pool: web::Data<Pool<NoTls>>,
+) -> Result<HttpResponse, HttpError> {
+    reorder_lists_request.validate()?;
+    let group_id = path.into_inner().0;
+
+    let mut client = db_pool.get().await?;
+    super::authorize_group(&req, &group_id, Permission::EditItems, &client).await?;
+
+    if db::models::List::reorder(&group_id, &reorder_lists_request.list_ids, &mut client).await?
+        == 0
+    {
+        return Err(HttpError::BadRequest(
+            "List ids do not belong to the group".to_string(),
+        ));
+    }
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+async fn archive_list(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    archive_list_request: web::Json<models::ArchiveListRequest>,
+    db_pool: web::Data<Pool<NoTls>>,
+) -> Result<HttpResponse, HttpError> {
+    let (group_id, list_id) = path.into_inner();
+
+    let client = db_pool.get().await?;
+    super::authorize_group(&req, &group_id, Permission::EditItems, &client).await?;
+
+    if db::models::List::set_archived(&list_id, &group_id, archive_list_request.archived, &client)
+        .await?
+        == 0
+    {
+        return Err(HttpError::NotFound);
+    }
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+async fn get_purchased_items(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    query: web::Query<models::PaginationQuery>,
+    db_pool: web::Data<Pool<NoTls>>,
+) -> Result<HttpResponse, HttpError> {
+    query.validate()?;
+    let (group_id, list_id) = path.into_inner();
+
+    let client = db_pool.get().await?;
+    super::authorize_group(&req, &group_id, Permission::ViewGroup, &client).await?;
+
+    let purchased_items = db::models::PurchasedItem::get_by_list(
+        &group_id,
+        &list_id,
+        query.limit.unwrap_or(constants::DEFAULT_PAGE_LIMIT),
+        query.offset.unwrap_or(0),
+        &client,
+    )
+    .await?
+    .into_iter()
+    .map(models::PurchasedItem::from)
+    .collect::<Vec<models::PurchasedItem>>();
+
+    Ok(HttpResponse::Ok().json(serde_json::to_string(&purchased_items)?))
+}
+
+pub fn list_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/group/{group_id}/lists", web::post().to(create_list))
+        .route("/group/{group_id}/lists", web::get().to(get_lists))
+        .route(
+            "/group/{group_id}/lists/order",
+            web::put().to(reorder_lists),
+        )
+        .route(
+            "/group/{group_id}/lists/{list_id}",
+            web::put().to(rename_list),
+        )
+        .route(
+            "/group/{group_id}/lists/{list_id}/archive",
+            web::put().to(archive_list),
+        )
+        .route(
+            "/group/{group_id}/lists/{list_id}/purchases",
+            web::get().to(get_purchased_items),
+        );
+}
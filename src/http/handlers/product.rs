@@ -0,0 +1,112 @@
+use crate::constants;
+use crate::db;
+use crate::http::error::HttpError;
+use crate::http::models;
+use actix_web::{web, HttpResponse, Result};
+use deadpool_postgres::Pool;
+use tokio_postgres::NoTls;
+use validator::Validate;
+
+async fn create_product(
+    req: actix_web::HttpRequest,
+    create_product_request: web::Json<models::CreateProductRequest>,
+    db_pool: web::Data<Pool<NoTls>>,
+) -> Result<HttpResponse, HttpError> {
+    super::get_auth_claims(&req)?;
+    create_product_request.validate()?;
+
+    let product = db::models::Product::from(create_product_request.into_inner());
+
+    let client = db_pool.get().await?;
+    product.insert(&client).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::to_string(&models::Product::from(product))?))
+}
+
+async fn get_product(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid,)>,
+    db_pool: web::Data<Pool<NoTls>>,
+) -> Result<HttpResponse, HttpError> {
+    super::get_auth_claims(&req)?;
+    let product_id = path.into_inner().0;
+
+    let client = db_pool.get().await?;
+    let product = db::models::Product::get_by_id(&product_id, &client)
+        .await?
+        .ok_or(HttpError::NotFound)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::to_string(&models::Product::from(product))?))
+}
+
+async fn update_product(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid,)>,
+    update_product_request: web::Json<models::UpdateProductRequest>,
+    db_pool: web::Data<Pool<NoTls>>,
+) -> Result<HttpResponse, HttpError> {
+    super::get_auth_claims(&req)?;
+    update_product_request.validate()?;
+    let product_id = path.into_inner().0;
+    let update_product_request = update_product_request.into_inner();
+
+    let product = db::models::Product {
+        id: product_id,
+        name: update_product_request.name,
+        description: update_product_request.description,
+        image: update_product_request.image,
+    };
+
+    let client = db_pool.get().await?;
+    if product.update(&client).await? == 0 {
+        return Err(HttpError::NotFound);
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::to_string(&models::Product::from(product))?))
+}
+
+async fn delete_product(
+    req: actix_web::HttpRequest,
+    path: web::Path<(uuid::Uuid,)>,
+    db_pool: web::Data<Pool<NoTls>>,
+) -> Result<HttpResponse, HttpError> {
+    super::get_auth_claims(&req)?;
+    let product_id = path.into_inner().0;
+
+    let client = db_pool.get().await?;
+    if db::models::Product::delete(&product_id, &client).await? == 0 {
+        return Err(HttpError::NotFound);
+    }
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+async fn get_products(
+    req: actix_web::HttpRequest,
+    query: web::Query<models::PaginationQuery>,
+    db_pool: web::Data<Pool<NoTls>>,
+) -> Result<HttpResponse, HttpError> {
+    super::get_auth_claims(&req)?;
+    query.validate()?;
+
+    let client = db_pool.get().await?;
+    let products = db::models::Product::get_paginated(
+        query.limit.unwrap_or(constants::DEFAULT_PAGE_LIMIT),
+        query.offset.unwrap_or(0),
+        &client,
+    )
+    .await?
+    .into_iter()
+    .map(models::Product::from)
+    .collect::<Vec<models::Product>>();
+
+    Ok(HttpResponse::Ok().json(serde_json::to_string(&products)?))
+}
+
+pub fn product_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/product", web::post().to(create_product))
+        .route("/product", web::get().to(get_products))
+        .route("/product/{product_id}", web::get().to(get_product))
+        .route("/product/{product_id}", web::put().to(update_product))
+        .route("/product/{product_id}", web::delete().to(delete_product));
+}
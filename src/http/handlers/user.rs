@@ -1,19 +1,23 @@
 use crate::db;
-use crate::db::models::Group;
 use crate::http::error::HttpError;
+use crate::http::jwt::{decode_jwt, Claims};
 use crate::http::models::User;
-use crate::http::{
-    jwt::{create_jwt, decode_jwt, Claims},
-    models,
-};
+use crate::http::{jwt::create_jwt, models};
 use crate::{
     constants,
-    messages::{websocket::WebsocketMessage, workers::WorkerMessageRequest},
+    messages::{
+        websocket::{AckResponse, AuthenticateRequest, ErrorCode, ErrorResponse, WebsocketMessage},
+        workers::{ClientSession, WorkerMessageRequest},
+    },
 };
+use actix_web::http::header::{HeaderValue, AUTHORIZATION, SEC_WEBSOCKET_PROTOCOL};
 use actix_web::{web, HttpResponse, Responder, Result};
-use actix_ws::Message;
+use actix_ws::{CloseCode, CloseReason, Message};
+use chrono::Utc;
 use deadpool_postgres::{Client, Pool};
+use dotenv::dotenv;
 use futures_util::StreamExt as _;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 use tokio_postgres::NoTls;
 use validator::Validate;
@@ -83,63 +87,339 @@ async fn login(
 async fn ws(
     req: actix_web::HttpRequest,
     stream: web::Payload,
+    query: web::Query<models::WebsocketQuery>,
     state_sender: web::Data<mpsc::UnboundedSender<WorkerMessageRequest>>,
+    websocket_config: web::Data<WebsocketConfig>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let claims = super::get_auth_claims(&req)?;
-    let (res, session, mut stream) = actix_ws::handle(&req, stream)?;
+    let query = query.into_inner();
+    let (mut claims, protocol_token) = get_websocket_auth_claims(&req, query.token.as_deref())?;
+    let (mut res, session, mut stream) = actix_ws::handle(&req, stream)?;
+    if protocol_token {
+        // Browsers drop the connection unless the server selects one of the offered protocols.
+        res.headers_mut().insert(
+            SEC_WEBSOCKET_PROTOCOL,
+            HeaderValue::from_static(constants::WEBSOCKET_TOKEN_PROTOCOL),
+        );
+    }
     println!("WebSocket handshake successful!"); // Log when handshake is successful
+    let mut reply_session = session.clone();
+    let session_id = uuid::Uuid::new_v4();
+    let device_id = query.device_id.unwrap_or_else(|| "default".to_string());
     state_sender
-        .send(WorkerMessageRequest::ClientLogin(claims.sub, session))
+        .send(WorkerMessageRequest::ClientLogin(
+            claims.sub,
+            ClientSession {
+                session_id,
+                device_id,
+                websocket_session: session,
+            },
+        ))
         .expect(constants::FAILED_TO_SEND_MESSAGE_TO_STATE_WORKER);
 
+    let idle_timeout = websocket_config.idle_timeout;
+
     actix_web::rt::spawn(async move {
-        while let Some(msg) = stream.next().await {
-            match msg {
-                Ok(Message::Text(text)) => {
-                    let received_message: WebsocketMessage = match serde_json::from_str(&text) {
-                        Ok(msg) => msg,
+        let mut heartbeat = actix_web::rt::time::interval(Duration::from_secs(
+            constants::WEBSOCKET_HEARTBEAT_INTERVAL_SECONDS,
+        ));
+        let mut last_heartbeat = Instant::now();
+
+        let close_reason = loop {
+            tokio::select! {
+                msg = stream.next() => {
+                    let msg = match msg {
+                        Some(msg) => msg,
+                        None => break None,
+                    };
+                    last_heartbeat = Instant::now();
+
+                    match msg {
+                        Ok(Message::Text(text)) => {
+                            handle_text_message(
+                                &text,
+                                &mut claims,
+                                session_id,
+                                &mut reply_session,
+                                &state_sender,
+                            )
+                            .await;
+                        }
+                        Ok(Message::Ping(bytes)) => {
+                            if reply_session.pong(&bytes).await.is_err() {
+                                break None;
+                            }
+                        }
+                        Ok(Message::Pong(_)) => {}
+                        Ok(Message::Close(reason)) => break reason,
+                        Ok(Message::Binary(_)) | Ok(Message::Continuation(_)) => {
+                            break Some(close_reason(
+                                CloseCode::Unsupported,
+                                "Only text messages are supported",
+                            ));
+                        }
+                        Ok(Message::Nop) => {}
                         Err(e) => {
-                            eprintln!("Failed to deserialize message: {:?}", e);
-                            continue;
+                            eprintln!("Websocket protocol error: {:?}", e);
+                            break Some(close_reason(CloseCode::Protocol, "Protocol violation"));
                         }
-                    };
+                    }
+                }
+                _ = heartbeat.tick() => {
+                    if claims.exp <= Utc::now().timestamp() as usize {
+                        break Some(close_reason(CloseCode::Policy, "Token expired"));
+                    }
 
-                    let websocket_request_message =
-                        if let WebsocketMessage::Request(msg) = received_message {
-                            msg
-                        } else {
-                            println!("Received invalid message in handler");
-                            continue;
-                        };
-
-                    if websocket_request_message.sender_id() != claims.sub {
-                        println!("Unauthorized websocket message");
-                        state_sender
-                            .send(WorkerMessageRequest::ClientShutdown(claims.sub))
-                            .expect(constants::FAILED_TO_SEND_MESSAGE_TO_STATE_WORKER);
+                    if last_heartbeat.elapsed() > idle_timeout {
+                        break Some(close_reason(CloseCode::Away, "Idle timeout"));
                     }
 
-                    state_sender
-                        .send(WorkerMessageRequest::WebsocketMessage(
-                            websocket_request_message,
-                        ))
-                        .expect("State mpsc sender crashed");
-                }
-                Ok(Message::Close(_)) => {
-                    state_sender
-                        .send(WorkerMessageRequest::ClientShutdown(claims.sub))
-                        .expect(constants::FAILED_TO_SEND_MESSAGE_TO_STATE_WORKER);
-                }
-                _ => {
-                    println!("Received other message type");
+                    if reply_session.ping(b"").await.is_err() {
+                        break None;
+                    }
                 }
             }
-        }
+        };
+
+        // The session is already closed when the worker shut it down or the client went away.
+        let _ = reply_session.close(close_reason).await;
+
+        // Sent once the stream ends too, so dropped connections also store their sync cursor.
+        state_sender
+            .send(WorkerMessageRequest::ClientShutdown(claims.sub, session_id))
+            .expect(constants::FAILED_TO_SEND_MESSAGE_TO_STATE_WORKER);
     });
 
     Ok(res)
 }
 
+async fn handle_text_message(
+    text: &str,
+    claims: &mut Claims,
+    session_id: uuid::Uuid,
+    reply_session: &mut actix_ws::Session,
+    state_sender: &mpsc::UnboundedSender<WorkerMessageRequest>,
+) {
+    let received_message: WebsocketMessage = match serde_json::from_str(text) {
+        Ok(msg) => msg,
+        Err(e) => {
+            eprintln!("Failed to deserialize message: {:?}", e);
+            send_error(
+                reply_session,
+                raw_request_id(text),
+                ErrorCode::InvalidMessage,
+                e.to_string(),
+            )
+            .await;
+            return;
+        }
+    };
+
+    let mut websocket_request = match received_message {
+        WebsocketMessage::Request(msg) => msg,
+        WebsocketMessage::Authenticate(authenticate) => {
+            reauthenticate(authenticate, claims, reply_session).await;
+            return;
+        }
+        WebsocketMessage::SyncAck(checkpoint) => {
+            state_sender
+                .send(WorkerMessageRequest::SyncAck(
+                    claims.sub, session_id, checkpoint,
+                ))
+                .expect(constants::FAILED_TO_SEND_MESSAGE_TO_STATE_WORKER);
+            return;
+        }
+        WebsocketMessage::Response(_) | WebsocketMessage::SyncCheckpoint(_) => {
+            send_error(
+                reply_session,
+                None,
+                ErrorCode::InvalidMessage,
+                "Expected a request message".to_string(),
+            )
+            .await;
+            return;
+        }
+    };
+    let request_id = websocket_request.request_id.clone();
+
+    if let Err(message) = websocket_request.message.validate() {
+        send_error(
+            reply_session,
+            request_id,
+            ErrorCode::InvalidRequest,
+            message,
+        )
+        .await;
+        return;
+    }
+
+    if !websocket_request.message.is_client_request() {
+        send_error(
+            reply_session,
+            request_id,
+            ErrorCode::InvalidRequest,
+            "Request has to be sent through the REST API".to_string(),
+        )
+        .await;
+        return;
+    }
+
+    if websocket_request.message.sender_id() != claims.sub {
+        println!("Unauthorized websocket message");
+        send_error(
+            reply_session,
+            request_id,
+            ErrorCode::Unauthorized,
+            "Sender does not match the authenticated user".to_string(),
+        )
+        .await;
+        return;
+    }
+
+    websocket_request.session_id = Some(session_id);
+    state_sender
+        .send(WorkerMessageRequest::WebsocketMessage(websocket_request))
+        .expect("State mpsc sender crashed");
+}
+
+// Replaces the claims of the connection, which moves its expiry. The token has to belong to the
+// user the connection was opened for.
+async fn reauthenticate(
+    authenticate: AuthenticateRequest,
+    claims: &mut Claims,
+    reply_session: &mut actix_ws::Session,
+) {
+    let new_claims = match decode_jwt(&authenticate.token) {
+        Ok(new_claims) if new_claims.sub == claims.sub => new_claims,
+        Ok(_) => {
+            send_error(
+                reply_session,
+                authenticate.request_id,
+                ErrorCode::Unauthorized,
+                "Token belongs to a different user".to_string(),
+            )
+            .await;
+            return;
+        }
+        Err(e) => {
+            send_error(
+                reply_session,
+                authenticate.request_id,
+                ErrorCode::Unauthorized,
+                e.to_string(),
+            )
+            .await;
+            return;
+        }
+    };
+    *claims = new_claims;
+
+    if let Some(request_id) = authenticate.request_id {
+        let ack: WebsocketMessage = AckResponse { request_id }.into();
+        if reply_session
+            .text(serde_json::to_string(&ack).expect("Failed to serialize websocket message"))
+            .await
+            .is_err()
+        {
+            println!("Failed to send websocket ack message");
+        }
+    }
+}
+
+// Besides the Authorization header, the token is accepted as the `token` query parameter or as
+// `Sec-WebSocket-Protocol: bearer, <token>`. Returns whether the protocol header was used.
+fn get_websocket_auth_claims(
+    req: &actix_web::HttpRequest,
+    query_token: Option<&str>,
+) -> Result<(Claims, bool), HttpError> {
+    if req.headers().contains_key(AUTHORIZATION) {
+        return Ok((super::get_auth_claims(req)?, false));
+    }
+
+    if let Some(token) = query_token {
+        return Ok((decode_jwt(token)?, false));
+    }
+
+    let protocol_token = req
+        .headers()
+        .get(SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|val| val.to_str().ok())
+        .and_then(|val| {
+            let mut protocols = val.split(',').map(str::trim);
+            match (protocols.next(), protocols.next()) {
+                (Some(protocol), Some(token))
+                    if protocol == constants::WEBSOCKET_TOKEN_PROTOCOL =>
+                {
+                    Some(token)
+                }
+                _ => None,
+            }
+        })
+        .ok_or(HttpError::Unauthorized)?;
+
+    Ok((decode_jwt(protocol_token)?, true))
+}
+
+fn close_reason(code: CloseCode, description: &str) -> CloseReason {
+    CloseReason {
+        code,
+        description: Some(description.to_string()),
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct WebsocketConfig {
+    pub idle_timeout: Duration,
+}
+
+impl WebsocketConfig {
+    pub fn from_env() -> Self {
+        dotenv().ok();
+        let seconds = std::env::var("WEBSOCKET_IDLE_TIMEOUT_SECONDS")
+            .map(|seconds| {
+                seconds
+                    .parse()
+                    .expect("WEBSOCKET_IDLE_TIMEOUT_SECONDS should be a number")
+            })
+            .unwrap_or(constants::DEFAULT_WEBSOCKET_IDLE_TIMEOUT_SECONDS);
+
+        WebsocketConfig {
+            idle_timeout: Duration::from_secs(seconds),
+        }
+    }
+}
+
+// Best effort lookup of the request id in a message that failed to deserialize, so the client
+// can still correlate the error.
+fn raw_request_id(text: &str) -> Option<String> {
+    serde_json::from_str::<serde_json::Value>(text)
+        .ok()?
+        .get("data")?
+        .get("request_id")?
+        .as_str()
+        .map(String::from)
+}
+
+async fn send_error(
+    session: &mut actix_ws::Session,
+    request_id: Option<String>,
+    code: ErrorCode,
+    message: String,
+) {
+    let error: WebsocketMessage = ErrorResponse {
+        request_id,
+        code,
+        message,
+    }
+    .into();
+    if session
+        .text(serde_json::to_string(&error).expect("Failed to serialize websocket message"))
+        .await
+        .is_err()
+    {
+        println!("Failed to send websocket error message");
+    }
+}
+
 async fn get_unhandled_join_group_requests(
     req: actix_web::HttpRequest,
     db_pool: web::Data<Pool<NoTls>>,
@@ -14,7 +14,7 @@ pub struct Claims {
 impl From<(&uuid::Uuid, &str)> for Claims {
     fn from(value: (&uuid::Uuid, &str)) -> Self {
         Claims {
-            sub: value.0.clone(),
+            sub: *value.0,
             email: value.1.to_string(),
             iat: Utc::now().timestamp() as usize,
             exp: (Utc::now() + Duration::hours(1)).timestamp() as usize,
@@ -1,5 +1,22 @@
+pub mod chat;
 pub mod group;
+pub mod inbox;
+pub mod invite;
+pub mod item;
+pub mod list;
+pub mod product;
 pub mod user;
 
-pub use group::{ApproveJoin, CreateGroupRequest, Group};
-pub use user::{LoginRequest, LoginResponse, User, UserCreateRequest};
+pub use chat::{ChatHistoryPage, ChatHistoryQuery};
+pub use group::{
+    ApproveJoin, CreateGroupRequest, Group, MemberPresence, RenameGroupRequest,
+    SetMemberRoleRequest, TransferGroupOwnershipRequest,
+};
+pub use inbox::InboxEntry;
+pub use invite::{CreateEmailInviteRequest, CreateInviteRequest, GroupInvite};
+pub use item::{GroupItemsQuery, Item, PurchasedItem};
+pub use list::{
+    ArchiveListRequest, CreateListRequest, List, ListsQuery, RenameListRequest, ReorderListsRequest,
+};
+pub use product::{CreateProductRequest, PaginationQuery, Product, UpdateProductRequest};
+pub use user::{LoginRequest, LoginResponse, User, UserCreateRequest, WebsocketQuery};
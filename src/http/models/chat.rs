@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Deserialize, Validate, Debug, Clone)]
+pub struct ChatHistoryQuery {
+    // Sequence of the oldest message of the previous page.
+    pub before: Option<i32>,
+    #[validate(range(min = 1, max = 100))]
+    pub limit: Option<i64>,
+}
+
+// Messages are ordered newest first. `next_cursor` is passed as `before` to get the next page
+// and is missing on the last one.
+#[derive(Serialize, Debug)]
+pub struct ChatHistoryPage<T> {
+    pub messages: Vec<T>,
+    pub next_cursor: Option<i32>,
+}
@@ -1,9 +1,9 @@
-use std::fmt;
-
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 
 use crate::db;
+use crate::messages::websocket::PresenceStatus;
+use crate::permissions::GroupRole;
 
 #[derive(Deserialize, Validate, Debug, Clone)]
 pub struct CreateGroupRequest {
@@ -12,6 +12,22 @@ pub struct CreateGroupRequest {
     pub name: String,
 }
 
+#[derive(Deserialize, Validate, Debug, Clone)]
+pub struct RenameGroupRequest {
+    #[validate(length(min = 2))]
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TransferGroupOwnershipRequest {
+    pub new_owner_id: uuid::Uuid,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SetMemberRoleRequest {
+    pub role: GroupRole,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Group {
     pub group_id: uuid::Uuid,
@@ -29,6 +45,14 @@ impl From<db::models::Group> for Group {
     }
 }
 
+#[derive(Serialize, Debug, Clone)]
+pub struct MemberPresence {
+    pub user_id: uuid::Uuid,
+    pub status: PresenceStatus,
+    pub last_seen: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
 pub struct ApproveJoin {
     pub candidate_id: uuid::Uuid,
     pub approved: bool,
@@ -0,0 +1,40 @@
+use serde::Serialize;
+
+use crate::db;
+use crate::messages::websocket::Conversation;
+
+#[derive(Serialize, Debug)]
+pub struct InboxMessage {
+    pub message: String,
+    pub sender_id: uuid::Uuid,
+    pub sequence: i32,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<db::models::inbox::InboxMessage> for InboxMessage {
+    fn from(value: db::models::inbox::InboxMessage) -> Self {
+        Self {
+            message: value.message,
+            sender_id: value.sender_id,
+            sequence: value.sequence,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct InboxEntry {
+    pub conversation: Conversation,
+    pub last_message: Option<InboxMessage>,
+    pub unread_count: i64,
+}
+
+impl From<db::models::InboxEntry> for InboxEntry {
+    fn from(value: db::models::InboxEntry) -> Self {
+        Self {
+            conversation: value.conversation,
+            last_message: value.last_message.map(InboxMessage::from),
+            unread_count: value.unread_count,
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::db;
+use crate::permissions::GroupRole;
+
+#[derive(Deserialize, Validate, Debug, Clone)]
+pub struct CreateInviteRequest {
+    pub role: Option<GroupRole>,
+    #[validate(range(min = 1))]
+    pub max_uses: Option<i32>,
+    #[validate(range(min = 1))]
+    pub expires_in_hours: Option<i64>,
+}
+
+#[derive(Deserialize, Validate, Debug, Clone)]
+pub struct CreateEmailInviteRequest {
+    #[validate(email(message = "Invalid email format"))]
+    pub email: String,
+    pub role: Option<GroupRole>,
+    #[validate(range(min = 1))]
+    pub expires_in_hours: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct GroupInvite {
+    pub invite_id: uuid::Uuid,
+    pub group_id: uuid::Uuid,
+    pub token: String,
+    pub created_by_user: uuid::Uuid,
+    pub role: GroupRole,
+    pub max_uses: Option<i32>,
+    pub uses: i32,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub email: Option<String>,
+}
+
+impl From<db::models::GroupInvite> for GroupInvite {
+    fn from(value: db::models::GroupInvite) -> Self {
+        Self {
+            invite_id: value.id,
+            group_id: value.group_id,
+            token: value.token,
+            created_by_user: value.created_by_user,
+            role: value.role,
+            max_uses: value.max_uses,
+            uses: value.uses,
+            expires_at: value.expires_at,
+            created_at: value.created_at,
+            email: value.email,
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+use crate::db;
+use crate::units::{ProductUnit, Quantity};
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl From<SortOrder> for db::models::SortOrder {
+    fn from(value: SortOrder) -> Self {
+        match value {
+            SortOrder::Asc => db::models::SortOrder::Asc,
+            SortOrder::Desc => db::models::SortOrder::Desc,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GroupItemsQuery {
+    pub list_id: Option<uuid::Uuid>,
+    pub name: Option<String>,
+    pub sort: Option<SortOrder>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Item {
+    pub item_id: uuid::Uuid,
+    pub product_id: uuid::Uuid,
+    pub product_name: String,
+    pub group_id: uuid::Uuid,
+    pub list_id: uuid::Uuid,
+    pub product_unit: ProductUnit,
+    pub quantity: Quantity,
+    pub note: Option<String>,
+    pub purchased_by: Option<uuid::Uuid>,
+    pub purchased_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl From<db::models::ItemWithProduct> for Item {
+    fn from(value: db::models::ItemWithProduct) -> Self {
+        Self {
+            item_id: value.id,
+            product_id: value.product_id,
+            product_name: value.product_name,
+            group_id: value.group_id,
+            list_id: value.list_id,
+            product_unit: value.unit,
+            quantity: value.quantity,
+            note: value.note,
+            purchased_by: value.purchased_by,
+            purchased_at: value.purchased_at,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct PurchasedItem {
+    pub item_id: uuid::Uuid,
+    pub product_id: uuid::Uuid,
+    pub product_name: String,
+    pub group_id: uuid::Uuid,
+    pub list_id: uuid::Uuid,
+    pub product_unit: ProductUnit,
+    pub quantity: Quantity,
+    pub note: Option<String>,
+    pub purchased_by: Option<uuid::Uuid>,
+    pub purchased_at: chrono::DateTime<chrono::Utc>,
+    pub cleared_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<db::models::PurchasedItem> for PurchasedItem {
+    fn from(value: db::models::PurchasedItem) -> Self {
+        Self {
+            item_id: value.id,
+            product_id: value.product_id,
+            product_name: value.product_name,
+            group_id: value.group_id,
+            list_id: value.list_id,
+            product_unit: value.unit,
+            quantity: value.quantity,
+            note: value.note,
+            purchased_by: value.purchased_by,
+            purchased_at: value.purchased_at,
+            cleared_at: value.cleared_at,
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::db;
+
+#[derive(Deserialize, Validate, Debug, Clone)]
+pub struct CreateListRequest {
+    #[validate(length(min = 1))]
+    pub name: String,
+}
+
+#[derive(Deserialize, Validate, Debug, Clone)]
+pub struct RenameListRequest {
+    #[validate(length(min = 1))]
+    pub name: String,
+}
+
+#[derive(Deserialize, Validate, Debug, Clone)]
+pub struct ReorderListsRequest {
+    #[validate(length(min = 1))]
+    pub list_ids: Vec<uuid::Uuid>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ArchiveListRequest {
+    pub archived: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ListsQuery {
+    pub include_archived: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct List {
+    pub list_id: uuid::Uuid,
+    pub group_id: uuid::Uuid,
+    pub name: String,
+    pub position: i32,
+    pub archived: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<db::models::List> for List {
+    fn from(value: db::models::List) -> Self {
+        Self {
+            list_id: value.id,
+            group_id: value.group_id,
+            name: value.name,
+            position: value.position,
+            archived: value.archived,
+            created_at: value.created_at,
+        }
+    }
+}
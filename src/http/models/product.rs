@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::db;
+
+#[derive(Deserialize, Validate, Debug, Clone)]
+pub struct CreateProductRequest {
+    #[validate(length(min = 2))]
+    pub name: String,
+    pub description: Option<String>,
+    pub image: Option<String>,
+}
+
+#[derive(Deserialize, Validate, Debug, Clone)]
+pub struct UpdateProductRequest {
+    #[validate(length(min = 2))]
+    pub name: String,
+    pub description: Option<String>,
+    pub image: Option<String>,
+}
+
+#[derive(Deserialize, Validate, Debug, Clone)]
+pub struct PaginationQuery {
+    #[validate(range(min = 1, max = 100))]
+    pub limit: Option<i64>,
+    #[validate(range(min = 0))]
+    pub offset: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Product {
+    pub product_id: uuid::Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub image: Option<String>,
+}
+
+impl From<db::models::Product> for Product {
+    fn from(value: db::models::Product) -> Self {
+        Self {
+            product_id: value.id,
+            name: value.name,
+            description: value.description,
+            image: value.image,
+        }
+    }
+}
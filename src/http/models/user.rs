@@ -31,6 +31,15 @@ pub struct LoginResponse {
     pub auth: String,
 }
 
+// Clients connecting from several devices pass a stable device id so each device keeps its
+// own sync cursor. Browsers can not set the Authorization header on the upgrade, so they may
+// pass the token here instead.
+#[derive(Debug, Deserialize)]
+pub struct WebsocketQuery {
+    pub device_id: Option<String>,
+    pub token: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct User {
     pub id: uuid::Uuid,
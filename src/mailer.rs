@@ -0,0 +1,190 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use dotenv::dotenv;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+#[derive(Debug, Clone)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug)]
+pub enum MailerError {
+    Address(String),
+    Build(String),
+    Delivery(String),
+}
+
+impl fmt::Display for MailerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MailerError::Address(address) => write!(f, "Invalid address: {}", address),
+            MailerError::Build(message) => write!(f, "Failed to build mail: {}", message),
+            MailerError::Delivery(message) => write!(f, "Failed to deliver mail: {}", message),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum Mailer {
+    Smtp {
+        transport: AsyncSmtpTransport<Tokio1Executor>,
+        from: Mailbox,
+    },
+    File(PathBuf),
+    // Keeps the latest MEMORY_OUTBOX_LIMIT mails instead of sending them, for tests.
+    Memory(Arc<Mutex<VecDeque<Mail>>>),
+}
+
+const MEMORY_OUTBOX_LIMIT: usize = 100;
+
+impl Mailer {
+    pub fn from_env() -> Self {
+        dotenv().ok();
+        // Without MAILER mails are written to MAIL_DIR, so they can still be read in development.
+        // Deployments that should actually send them set MAILER=smtp.
+        let backend = std::env::var("MAILER").unwrap_or_else(|_| "file".to_string());
+
+        match backend.as_str() {
+            "smtp" => {
+                let host = std::env::var("SMTP_HOST").expect("SMTP_HOST should be set");
+                let from = std::env::var("MAIL_FROM")
+                    .expect("MAIL_FROM should be set")
+                    .parse()
+                    .expect("MAIL_FROM should be a valid address");
+
+                let mut builder = AsyncSmtpTransport::<Tokio1Executor>::relay(&host)
+                    .expect("Failed to create SMTP transport");
+                if let Ok(port) = std::env::var("SMTP_PORT") {
+                    builder = builder.port(port.parse().expect("SMTP_PORT should be a number"));
+                }
+                if let (Ok(username), Ok(password)) = (
+                    std::env::var("SMTP_USERNAME"),
+                    std::env::var("SMTP_PASSWORD"),
+                ) {
+                    builder = builder.credentials(Credentials::new(username, password));
+                }
+
+                Mailer::Smtp {
+                    transport: builder.build(),
+                    from,
+                }
+            }
+            "file" => {
+                let dir = std::env::var("MAIL_DIR").unwrap_or_else(|_| "mail".to_string());
+                Mailer::File(PathBuf::from(dir))
+            }
+            "memory" => Mailer::Memory(Arc::new(Mutex::new(VecDeque::new()))),
+            backend => panic!("Unknown MAILER backend: {}", backend),
+        }
+    }
+
+    pub async fn send(&self, mail: Mail) -> Result<(), MailerError> {
+        match self {
+            Mailer::Smtp { transport, from } => {
+                let to: Mailbox = mail
+                    .to
+                    .parse()
+                    .map_err(|_| MailerError::Address(mail.to.clone()))?;
+                let message = Message::builder()
+                    .from(from.clone())
+                    .to(to)
+                    .subject(mail.subject)
+                    .body(mail.body)
+                    .map_err(|error| MailerError::Build(error.to_string()))?;
+
+                transport
+                    .send(message)
+                    .await
+                    .map_err(|error| MailerError::Delivery(error.to_string()))?;
+            }
+            Mailer::File(dir) => {
+                tokio::fs::create_dir_all(dir)
+                    .await
+                    .map_err(|error| MailerError::Delivery(error.to_string()))?;
+
+                let path = dir.join(format!(
+                    "{}-{}.txt",
+                    chrono::Utc::now().format("%Y%m%d%H%M%S"),
+                    uuid::Uuid::new_v4()
+                ));
+                let content = format!(
+                    "To: {}\nSubject: {}\n\n{}\n",
+                    mail.to, mail.subject, mail.body
+                );
+
+                tokio::fs::write(path, content)
+                    .await
+                    .map_err(|error| MailerError::Delivery(error.to_string()))?;
+            }
+            Mailer::Memory(outbox) => {
+                let mut outbox = outbox.lock().expect("Mailer outbox poisoned");
+                if outbox.len() == MEMORY_OUTBOX_LIMIT {
+                    outbox.pop_front();
+                }
+                outbox.push_back(mail);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mail(to: &str) -> Mail {
+        Mail {
+            to: to.to_string(),
+            subject: "Invite".to_string(),
+            body: "Redeem the invite code".to_string(),
+        }
+    }
+
+    fn outbox(mailer: &Mailer) -> Vec<String> {
+        match mailer {
+            Mailer::Memory(outbox) => outbox
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|mail| mail.to.clone())
+                .collect(),
+            _ => panic!("Expected the memory mailer"),
+        }
+    }
+
+    #[tokio::test]
+    async fn memory_mailer_keeps_sent_mails() {
+        let mailer = Mailer::Memory(Arc::new(Mutex::new(VecDeque::new())));
+        mailer.send(mail("first@example.com")).await.unwrap();
+        mailer.send(mail("second@example.com")).await.unwrap();
+
+        assert_eq!(
+            outbox(&mailer),
+            vec!["first@example.com", "second@example.com"]
+        );
+    }
+
+    #[tokio::test]
+    async fn memory_mailer_drops_the_oldest_mails_past_the_limit() {
+        let mailer = Mailer::Memory(Arc::new(Mutex::new(VecDeque::new())));
+        for index in 0..=MEMORY_OUTBOX_LIMIT {
+            mailer
+                .send(mail(&format!("{}@example.com", index)))
+                .await
+                .unwrap();
+        }
+
+        let outbox = outbox(&mailer);
+        assert_eq!(outbox.len(), MEMORY_OUTBOX_LIMIT);
+        assert_eq!(outbox[0], "1@example.com");
+    }
+}
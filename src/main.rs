@@ -1,31 +1,85 @@
 use db::make_db_pool;
 use dotenv::dotenv;
 
-use actix_web::{web, App, HttpServer};
-use http::handlers::{group_routes, user_routes};
+use actix_web::{rt, web, App, HttpServer};
+use http::handlers::{
+    chat_routes, group_routes, invite_routes, list_routes, product_routes, user_routes,
+    WebsocketConfig,
+};
 mod constants;
 mod db;
 mod http;
+mod mailer;
 mod messages;
+mod permissions;
+mod units;
 mod workers;
 
+use messages::workers::WorkerMessageRequest;
+use tokio::sync::oneshot;
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
 
     let pool = make_db_pool().await;
+    let mailer = mailer::Mailer::from_env();
+    let websocket_config = WebsocketConfig::from_env();
     let database_sender = workers::spawn_database_worker(pool.clone());
-    let message_worker_sender = workers::spawn_message_worker(database_sender, pool.clone());
+    let message_worker_sender =
+        workers::spawn_message_worker(database_sender.clone(), pool.clone());
+    let shutdown_sender = message_worker_sender.clone();
 
-    HttpServer::new(move || {
+    let server = HttpServer::new(move || {
         let message_worker_sender = message_worker_sender.clone();
         App::new()
             .app_data(web::Data::new(pool.clone()))
             .app_data(web::Data::new(message_worker_sender.clone()))
+            .app_data(web::Data::new(database_sender.clone()))
+            .app_data(web::Data::new(mailer.clone()))
+            .app_data(web::Data::new(websocket_config))
+            .configure(chat_routes)
             .configure(group_routes)
+            .configure(invite_routes)
+            .configure(list_routes)
+            .configure(product_routes)
             .configure(user_routes)
     })
     .bind(("127.0.0.1", 8080))?
-    .run()
-    .await
+    .disable_signals()
+    .run();
+
+    // Websockets are closed by the worker first, otherwise a graceful stop would wait on them
+    // until the shutdown timeout.
+    let server_handle = server.handle();
+    rt::spawn(async move {
+        shutdown_signal().await;
+        let (reply_sender, reply_receiver) = oneshot::channel();
+        if shutdown_sender
+            .send(WorkerMessageRequest::ServerShutdown(reply_sender))
+            .is_ok()
+        {
+            let _ = reply_receiver.await;
+        }
+        server_handle.stop(true).await;
+    });
+
+    server.await
+}
+
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate = rt::signal::unix::signal(rt::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM");
+        tokio::select! {
+            _ = rt::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = rt::signal::ctrl_c().await;
+    }
 }
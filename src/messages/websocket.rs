@@ -1,22 +1,67 @@
-use response::GroupChatMessageResponse;
 use serde::{Deserialize, Serialize};
 
+use crate::db::models::SyncCursor;
+use crate::permissions::GroupRole;
+use crate::units::{ProductUnit, Quantity};
+
 mod request;
 mod response;
 
 pub use request::AddItemRequest;
 pub use request::AddItemsRequest;
+pub use request::ClearPurchasedRequest;
 pub use request::DirectChatMessageRequest;
 pub use request::GroupChatMessageRequest;
+pub use request::MarkReadRequest;
+pub use request::TogglePurchasedRequest;
 pub use request::WebsocketMessageRequest;
+pub use request::WebsocketRequest;
+pub use response::AckResponse;
+pub use response::AddItemResponse;
 pub use response::AddItemsResponse;
+pub use response::ClearPurchasedResponse;
 pub use response::DirectChatMessageResponse;
+pub use response::ErrorCode;
+pub use response::ErrorResponse;
+pub use response::GroupChatMessageResponse;
+pub use response::ReadReceiptResponse;
+pub use response::TogglePurchasedResponse;
+pub use response::UnreadCountResponse;
 pub use response::WebsocketMessageResponse;
 
 pub trait GroupId {
     fn get_group_id(&self) -> &uuid::Uuid;
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Conversation {
+    Direct { peer_id: uuid::Uuid },
+    Group { group_id: uuid::Uuid },
+}
+
+// Ephemeral, only delivered to whoever is online and never stored.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TypingMessage {
+    pub sender_id: uuid::Uuid,
+    pub conversation: Conversation,
+    pub typing: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceStatus {
+    Online,
+    Offline,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct PresenceMessage {
+    pub user_id: uuid::Uuid,
+    pub status: PresenceStatus,
+    pub last_seen: Option<chrono::DateTime<chrono::Utc>>,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct ApproveJoin {
     pub candidate_id: uuid::Uuid,
@@ -32,10 +77,57 @@ pub struct JoinGroupRequest {
     pub group_id: uuid::Uuid,
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum GroupUpdate {
+    Renamed {
+        name: String,
+    },
+    OwnerChanged {
+        owner_id: uuid::Uuid,
+    },
+    MemberJoined {
+        user_id: uuid::Uuid,
+        role: GroupRole,
+    },
+    MemberRoleChanged {
+        user_id: uuid::Uuid,
+        role: GroupRole,
+    },
+    Deleted,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct GroupUpdateMessage {
+    pub sender_id: uuid::Uuid,
+    pub group_id: uuid::Uuid,
+    pub update: GroupUpdate,
+}
+
+impl GroupId for GroupUpdateMessage {
+    fn get_group_id(&self) -> &uuid::Uuid {
+        &self.group_id
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct MemberRemovedMessage {
+    pub sender_id: uuid::Uuid,
+    pub group_id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+}
+
+impl GroupId for MemberRemovedMessage {
+    fn get_group_id(&self) -> &uuid::Uuid {
+        &self.group_id
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct RemoveItemsMessage {
     pub sender_id: uuid::Uuid,
     pub group_id: uuid::Uuid,
+    pub list_id: uuid::Uuid,
     pub items: Vec<uuid::Uuid>,
 }
 
@@ -45,11 +137,71 @@ impl GroupId for RemoveItemsMessage {
     }
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct UpdateItemMessage {
+    pub item_id: uuid::Uuid,
+    pub product_unit: Option<ProductUnit>,
+    pub quantity: Option<Quantity>,
+    pub note: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct UpdateItemsMessage {
+    pub sender_id: uuid::Uuid,
+    pub group_id: uuid::Uuid,
+    pub list_id: uuid::Uuid,
+    pub items: Vec<UpdateItemMessage>,
+}
+
+impl GroupId for UpdateItemsMessage {
+    fn get_group_id(&self) -> &uuid::Uuid {
+        &self.group_id
+    }
+}
+
+// Presents a fresh token on an open connection, before the one it was opened with expires.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AuthenticateRequest {
+    pub request_id: Option<String>,
+    pub token: String,
+}
+
+// Positions in messages, group_messages and events. The server sends one once everything before
+// it was sent to the session, the client echoes it back as sync_ack after receiving it. Only
+// acknowledged positions move the sync cursor of the device.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncCheckpoint {
+    pub direct_sequence: i32,
+    pub group_sequence: i32,
+    pub event_sequence: i32,
+}
+
+impl SyncCheckpoint {
+    pub fn includes(&self, other: &SyncCheckpoint) -> bool {
+        other.direct_sequence <= self.direct_sequence
+            && other.group_sequence <= self.group_sequence
+            && other.event_sequence <= self.event_sequence
+    }
+}
+
+impl From<SyncCursor> for SyncCheckpoint {
+    fn from(value: SyncCursor) -> Self {
+        SyncCheckpoint {
+            direct_sequence: value.direct_sequence,
+            group_sequence: value.group_sequence,
+            event_sequence: value.event_sequence,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(tag = "type", content = "data", rename_all = "snake_case")]
 pub enum WebsocketMessage {
-    Request(WebsocketMessageRequest),
+    Request(WebsocketRequest),
     Response(WebsocketMessageResponse),
+    Authenticate(AuthenticateRequest),
+    SyncCheckpoint(SyncCheckpoint),
+    SyncAck(SyncCheckpoint),
 }
 
 impl From<DirectChatMessageResponse> for WebsocketMessage {
@@ -76,6 +228,24 @@ impl From<RemoveItemsMessage> for WebsocketMessage {
     }
 }
 
+impl From<UpdateItemsMessage> for WebsocketMessage {
+    fn from(value: UpdateItemsMessage) -> Self {
+        Self::Response(WebsocketMessageResponse::UpdateItems(value))
+    }
+}
+
+impl From<TogglePurchasedResponse> for WebsocketMessage {
+    fn from(value: TogglePurchasedResponse) -> Self {
+        Self::Response(WebsocketMessageResponse::TogglePurchased(value))
+    }
+}
+
+impl From<ClearPurchasedResponse> for WebsocketMessage {
+    fn from(value: ClearPurchasedResponse) -> Self {
+        Self::Response(WebsocketMessageResponse::ClearPurchased(value))
+    }
+}
+
 impl From<JoinGroupRequest> for WebsocketMessage {
     fn from(value: JoinGroupRequest) -> Self {
         Self::Response(WebsocketMessageResponse::JoinGroup(value))
@@ -87,3 +257,51 @@ impl From<ApproveJoin> for WebsocketMessage {
         Self::Response(WebsocketMessageResponse::ApproveJoin(value))
     }
 }
+
+impl From<GroupUpdateMessage> for WebsocketMessage {
+    fn from(value: GroupUpdateMessage) -> Self {
+        Self::Response(WebsocketMessageResponse::GroupUpdate(value))
+    }
+}
+
+impl From<MemberRemovedMessage> for WebsocketMessage {
+    fn from(value: MemberRemovedMessage) -> Self {
+        Self::Response(WebsocketMessageResponse::MemberRemoved(value))
+    }
+}
+
+impl From<ReadReceiptResponse> for WebsocketMessage {
+    fn from(value: ReadReceiptResponse) -> Self {
+        Self::Response(WebsocketMessageResponse::ReadReceipt(value))
+    }
+}
+
+impl From<TypingMessage> for WebsocketMessage {
+    fn from(value: TypingMessage) -> Self {
+        Self::Response(WebsocketMessageResponse::Typing(value))
+    }
+}
+
+impl From<PresenceMessage> for WebsocketMessage {
+    fn from(value: PresenceMessage) -> Self {
+        Self::Response(WebsocketMessageResponse::Presence(value))
+    }
+}
+
+impl From<UnreadCountResponse> for WebsocketMessage {
+    fn from(value: UnreadCountResponse) -> Self {
+        Self::Response(WebsocketMessageResponse::UnreadCount(value))
+    }
+}
+
+impl From<AckResponse> for WebsocketMessage {
+    fn from(value: AckResponse) -> Self {
+        Self::Response(WebsocketMessageResponse::Ack(value))
+    }
+}
+
+impl From<ErrorResponse> for WebsocketMessage {
+    fn from(value: ErrorResponse) -> Self {
+        Self::Response(WebsocketMessageResponse::Error(value))
+    }
+}
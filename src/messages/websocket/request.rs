@@ -1,13 +1,14 @@
 use serde::{Deserialize, Serialize};
 
-use super::ApproveJoin;
+use super::{ApproveJoin, Conversation};
+use crate::permissions::Permission;
+use crate::units::{self, ProductUnit, Quantity};
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct AddItemRequest {
     pub product_id: uuid::Uuid,
-    pub group_id: uuid::Uuid,
-    pub product_unit: String,
-    pub quantity: Option<f32>,
+    pub product_unit: ProductUnit,
+    pub quantity: Option<Quantity>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -28,9 +29,34 @@ pub struct GroupChatMessageRequest {
 pub struct AddItemsRequest {
     pub sender_id: uuid::Uuid,
     pub group_id: uuid::Uuid,
+    pub list_id: uuid::Uuid,
     pub items: Vec<AddItemRequest>,
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TogglePurchasedRequest {
+    pub sender_id: uuid::Uuid,
+    pub group_id: uuid::Uuid,
+    pub list_id: uuid::Uuid,
+    pub item_id: uuid::Uuid,
+    pub purchased: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ClearPurchasedRequest {
+    pub sender_id: uuid::Uuid,
+    pub group_id: uuid::Uuid,
+    pub list_id: uuid::Uuid,
+}
+
+// Marks every message of the conversation up to the sequence as read by the sender.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct MarkReadRequest {
+    pub sender_id: uuid::Uuid,
+    pub conversation: Conversation,
+    pub up_to_sequence: i32,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum WebsocketMessageRequest {
@@ -38,8 +64,33 @@ pub enum WebsocketMessageRequest {
     GroupChatMessage(GroupChatMessageRequest),
     AddItemsRequest(AddItemsRequest),
     RemoveItems(super::RemoveItemsMessage),
+    UpdateItems(super::UpdateItemsMessage),
+    TogglePurchased(TogglePurchasedRequest),
+    ClearPurchased(ClearPurchasedRequest),
     JoinGroup(super::JoinGroupRequest),
     ApproveJoin(super::ApproveJoin),
+    MarkRead(MarkReadRequest),
+    Typing(super::TypingMessage),
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct WebsocketRequest {
+    pub request_id: Option<String>,
+    #[serde(flatten)]
+    pub message: WebsocketMessageRequest,
+    // Set by the server to the session the request arrived on, replies go only there.
+    #[serde(skip)]
+    pub session_id: Option<uuid::Uuid>,
+}
+
+impl From<WebsocketMessageRequest> for WebsocketRequest {
+    fn from(value: WebsocketMessageRequest) -> Self {
+        Self {
+            request_id: None,
+            message: value,
+            session_id: None,
+        }
+    }
 }
 
 impl From<ApproveJoin> for WebsocketMessageRequest {
@@ -49,14 +100,101 @@ impl From<ApproveJoin> for WebsocketMessageRequest {
 }
 
 impl WebsocketMessageRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        let quantities: Vec<Option<Quantity>> = match self {
+            WebsocketMessageRequest::AddItemsRequest(msg) => {
+                msg.items.iter().map(|item| item.quantity).collect()
+            }
+            WebsocketMessageRequest::UpdateItems(msg) => {
+                msg.items.iter().map(|item| item.quantity).collect()
+            }
+            WebsocketMessageRequest::MarkRead(msg) if msg.up_to_sequence < 1 => {
+                return Err("Sequence must be positive".to_string());
+            }
+            _ => vec![],
+        };
+
+        if !quantities
+            .into_iter()
+            .flatten()
+            .all(units::is_valid_quantity)
+        {
+            return Err(format!(
+                "Quantity must be positive, at most {} and have at most {} decimal places",
+                units::MAX_QUANTITY,
+                units::MAX_QUANTITY_SCALE
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn is_client_request(&self) -> bool {
+        !matches!(
+            self,
+            WebsocketMessageRequest::JoinGroup(_) | WebsocketMessageRequest::ApproveJoin(_)
+        )
+    }
+
+    pub fn required_permission(&self) -> Option<(uuid::Uuid, Permission)> {
+        match self {
+            WebsocketMessageRequest::GroupChatMessage(msg) => {
+                Some((msg.group_id, Permission::PostMessages))
+            }
+            WebsocketMessageRequest::AddItemsRequest(msg) => {
+                Some((msg.group_id, Permission::EditItems))
+            }
+            WebsocketMessageRequest::RemoveItems(msg) => {
+                Some((msg.group_id, Permission::EditItems))
+            }
+            WebsocketMessageRequest::UpdateItems(msg) => {
+                Some((msg.group_id, Permission::EditItems))
+            }
+            WebsocketMessageRequest::TogglePurchased(msg) => {
+                Some((msg.group_id, Permission::EditItems))
+            }
+            WebsocketMessageRequest::ClearPurchased(msg) => {
+                Some((msg.group_id, Permission::EditItems))
+            }
+            WebsocketMessageRequest::MarkRead(msg) => match msg.conversation {
+                Conversation::Group { group_id } => Some((group_id, Permission::ViewGroup)),
+                Conversation::Direct { .. } => None,
+            },
+            WebsocketMessageRequest::Typing(msg) => match msg.conversation {
+                Conversation::Group { group_id } => Some((group_id, Permission::PostMessages)),
+                Conversation::Direct { .. } => None,
+            },
+            WebsocketMessageRequest::JoinGroup(_)
+            | WebsocketMessageRequest::ApproveJoin(_)
+            | WebsocketMessageRequest::DirectChatMessage(_) => None,
+        }
+    }
+
+    // Item requests address a list, which has to belong to the group of the request.
+    pub fn list_id(&self) -> Option<(uuid::Uuid, uuid::Uuid)> {
+        match self {
+            WebsocketMessageRequest::AddItemsRequest(msg) => Some((msg.group_id, msg.list_id)),
+            WebsocketMessageRequest::RemoveItems(msg) => Some((msg.group_id, msg.list_id)),
+            WebsocketMessageRequest::UpdateItems(msg) => Some((msg.group_id, msg.list_id)),
+            WebsocketMessageRequest::TogglePurchased(msg) => Some((msg.group_id, msg.list_id)),
+            WebsocketMessageRequest::ClearPurchased(msg) => Some((msg.group_id, msg.list_id)),
+            _ => None,
+        }
+    }
+
     pub fn sender_id(&self) -> uuid::Uuid {
         match self {
             WebsocketMessageRequest::GroupChatMessage(msg) => msg.sender_id,
             WebsocketMessageRequest::AddItemsRequest(msg) => msg.sender_id,
             WebsocketMessageRequest::RemoveItems(msg) => msg.sender_id,
+            WebsocketMessageRequest::UpdateItems(msg) => msg.sender_id,
+            WebsocketMessageRequest::TogglePurchased(msg) => msg.sender_id,
+            WebsocketMessageRequest::ClearPurchased(msg) => msg.sender_id,
             WebsocketMessageRequest::JoinGroup(msg) => msg.sender_id,
             WebsocketMessageRequest::ApproveJoin(msg) => msg.group_owner,
             WebsocketMessageRequest::DirectChatMessage(msg) => msg.sender_id,
+            WebsocketMessageRequest::MarkRead(msg) => msg.sender_id,
+            WebsocketMessageRequest::Typing(msg) => msg.sender_id,
         }
     }
 }
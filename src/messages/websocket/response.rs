@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
 
-use crate::db::models::chat_message::DirectChatMessage;
+use crate::db::models::chat_message::{DirectChatMessage, GroupChatMessage};
+use crate::units::{ProductUnit, Quantity};
 
+use super::Conversation;
 use super::DirectChatMessageRequest;
 use super::GroupChatMessageRequest;
 use super::GroupId;
@@ -16,6 +18,8 @@ pub struct DirectChatMessageResponse {
     pub read: bool,
     pub message: String,
     pub created_at: chrono::DateTime<chrono::Utc>,
+    // Reserved by the message worker before the message is broadcast.
+    pub sequence: i32,
 }
 
 impl From<DirectChatMessageRequest> for DirectChatMessageResponse {
@@ -27,6 +31,7 @@ impl From<DirectChatMessageRequest> for DirectChatMessageResponse {
             read: false,
             message: value.message.clone(),
             created_at: Utc::now(),
+            sequence: 0,
         }
     }
 }
@@ -40,6 +45,7 @@ impl From<DirectChatMessage> for DirectChatMessageResponse {
             receiver_id: value.receiver_id,
             read: value.read,
             created_at: value.created_at,
+            sequence: value.sequence,
         }
     }
 }
@@ -50,6 +56,9 @@ pub struct GroupChatMessageResponse {
     pub sender_id: uuid::Uuid,
     pub group_id: uuid::Uuid,
     pub message: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    // Reserved by the message worker before the message is broadcast.
+    pub sequence: i32,
 }
 
 impl GroupId for GroupChatMessageResponse {
@@ -58,6 +67,19 @@ impl GroupId for GroupChatMessageResponse {
     }
 }
 
+impl From<GroupChatMessage> for GroupChatMessageResponse {
+    fn from(value: GroupChatMessage) -> Self {
+        Self {
+            id: value.id,
+            sender_id: value.sender_id,
+            group_id: value.group_id,
+            message: value.message,
+            created_at: value.created_at,
+            sequence: value.sequence,
+        }
+    }
+}
+
 impl From<GroupChatMessageRequest> for GroupChatMessageResponse {
     fn from(value: GroupChatMessageRequest) -> Self {
         Self {
@@ -65,27 +87,34 @@ impl From<GroupChatMessageRequest> for GroupChatMessageResponse {
             sender_id: value.sender_id,
             group_id: value.group_id,
             message: value.message.clone(),
+            created_at: Utc::now(),
+            sequence: 0,
         }
     }
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct AddItemResponse {
-    id: uuid::Uuid,
-    product_id: uuid::Uuid,
-    group_id: uuid::Uuid,
-    product_unit: String,
-    quantity: Option<f32>,
+    pub id: uuid::Uuid,
+    pub product_id: uuid::Uuid,
+    pub group_id: uuid::Uuid,
+    pub list_id: uuid::Uuid,
+    pub product_unit: ProductUnit,
+    pub quantity: Quantity,
+    pub merged: bool,
 }
 
-impl From<super::AddItemRequest> for AddItemResponse {
-    fn from(value: super::AddItemRequest) -> Self {
+// Items are added to the group and list of the request they came with.
+impl From<(super::AddItemRequest, uuid::Uuid, uuid::Uuid)> for AddItemResponse {
+    fn from((value, group_id, list_id): (super::AddItemRequest, uuid::Uuid, uuid::Uuid)) -> Self {
         Self {
             id: uuid::Uuid::new_v4(),
             product_id: value.product_id,
-            group_id: value.group_id,
+            group_id,
+            list_id,
             product_unit: value.product_unit,
-            quantity: value.quantity,
+            quantity: value.quantity.unwrap_or(Quantity::ONE),
+            merged: false,
         }
     }
 }
@@ -101,6 +130,7 @@ pub struct AddItemsResponse {
     pub sender_id: uuid::Uuid,
     pub items: Vec<AddItemResponse>,
     pub group_id: uuid::Uuid,
+    pub list_id: uuid::Uuid,
 }
 
 impl From<super::AddItemsRequest> for AddItemsResponse {
@@ -108,7 +138,12 @@ impl From<super::AddItemsRequest> for AddItemsResponse {
         Self {
             sender_id: value.sender_id,
             group_id: value.group_id,
-            items: value.items.into_iter().map(AddItemResponse::from).collect(),
+            list_id: value.list_id,
+            items: value
+                .items
+                .into_iter()
+                .map(|item| AddItemResponse::from((item, value.group_id, value.list_id)))
+                .collect(),
         }
     }
 }
@@ -119,6 +154,117 @@ impl GroupId for AddItemsResponse {
     }
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TogglePurchasedResponse {
+    pub sender_id: uuid::Uuid,
+    pub group_id: uuid::Uuid,
+    pub list_id: uuid::Uuid,
+    pub item_id: uuid::Uuid,
+    pub purchased_by: Option<uuid::Uuid>,
+    pub purchased_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl From<super::TogglePurchasedRequest> for TogglePurchasedResponse {
+    fn from(value: super::TogglePurchasedRequest) -> Self {
+        let (purchased_by, purchased_at) = if value.purchased {
+            (Some(value.sender_id), Some(Utc::now()))
+        } else {
+            (None, None)
+        };
+
+        Self {
+            sender_id: value.sender_id,
+            group_id: value.group_id,
+            list_id: value.list_id,
+            item_id: value.item_id,
+            purchased_by,
+            purchased_at,
+        }
+    }
+}
+
+impl GroupId for TogglePurchasedResponse {
+    fn get_group_id(&self) -> &uuid::Uuid {
+        &self.group_id
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ClearPurchasedResponse {
+    pub sender_id: uuid::Uuid,
+    pub group_id: uuid::Uuid,
+    pub list_id: uuid::Uuid,
+    pub cleared_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<super::ClearPurchasedRequest> for ClearPurchasedResponse {
+    fn from(value: super::ClearPurchasedRequest) -> Self {
+        Self {
+            sender_id: value.sender_id,
+            group_id: value.group_id,
+            list_id: value.list_id,
+            cleared_at: Utc::now(),
+        }
+    }
+}
+
+impl GroupId for ClearPurchasedResponse {
+    fn get_group_id(&self) -> &uuid::Uuid {
+        &self.group_id
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ReadReceiptResponse {
+    pub reader_id: uuid::Uuid,
+    pub conversation: Conversation,
+    pub up_to_sequence: i32,
+    pub read_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<super::MarkReadRequest> for ReadReceiptResponse {
+    fn from(value: super::MarkReadRequest) -> Self {
+        Self {
+            reader_id: value.sender_id,
+            conversation: value.conversation,
+            up_to_sequence: value.up_to_sequence,
+            read_at: Utc::now(),
+        }
+    }
+}
+
+// Sent to recipients of a new chat message, so the inbox counters can be bumped without
+// reloading the inbox. The sequence is that of the new message.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct UnreadCountResponse {
+    pub conversation: Conversation,
+    pub unread_delta: i64,
+    pub sequence: i32,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InvalidMessage,
+    InvalidRequest,
+    Unauthorized,
+    NotMember,
+    Forbidden,
+    DatabaseError,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ErrorResponse {
+    pub request_id: Option<String>,
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AckResponse {
+    pub request_id: String,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum WebsocketMessageResponse {
@@ -126,8 +272,19 @@ pub enum WebsocketMessageResponse {
     GroupChatMessage(GroupChatMessageResponse),
     AddItems(AddItemsResponse),
     RemoveItems(super::RemoveItemsMessage),
+    UpdateItems(super::UpdateItemsMessage),
+    TogglePurchased(TogglePurchasedResponse),
+    ClearPurchased(ClearPurchasedResponse),
     JoinGroup(super::JoinGroupRequest),
     ApproveJoin(super::ApproveJoin),
+    GroupUpdate(super::GroupUpdateMessage),
+    MemberRemoved(super::MemberRemovedMessage),
+    ReadReceipt(ReadReceiptResponse),
+    UnreadCount(UnreadCountResponse),
+    Typing(super::TypingMessage),
+    Presence(super::PresenceMessage),
+    Ack(AckResponse),
+    Error(ErrorResponse),
 }
 
 impl WebsocketMessageResponse {
@@ -137,8 +294,19 @@ impl WebsocketMessageResponse {
             WebsocketMessageResponse::GroupChatMessage(_) => true,
             WebsocketMessageResponse::AddItems(_) => true,
             WebsocketMessageResponse::RemoveItems(_) => true,
-            WebsocketMessageResponse::JoinGroup(_) => false,
-            WebsocketMessageResponse::ApproveJoin(_) => false,
+            WebsocketMessageResponse::UpdateItems(_) => true,
+            WebsocketMessageResponse::TogglePurchased(_) => true,
+            WebsocketMessageResponse::ClearPurchased(_) => true,
+            WebsocketMessageResponse::JoinGroup(_) => true,
+            WebsocketMessageResponse::ApproveJoin(_) => true,
+            WebsocketMessageResponse::GroupUpdate(_) => false,
+            WebsocketMessageResponse::MemberRemoved(_) => false,
+            WebsocketMessageResponse::ReadReceipt(_) => true,
+            WebsocketMessageResponse::UnreadCount(_) => false,
+            WebsocketMessageResponse::Typing(_) => false,
+            WebsocketMessageResponse::Presence(_) => false,
+            WebsocketMessageResponse::Ack(_) => false,
+            WebsocketMessageResponse::Error(_) => false,
         }
     }
 }
@@ -155,8 +323,19 @@ impl From<WebsocketMessageRequest> for WebsocketMessageResponse {
                 WebsocketMessageResponse::AddItems(AddItemsResponse::from(msg))
             }
             WebsocketMessageRequest::RemoveItems(msg) => WebsocketMessageResponse::RemoveItems(msg),
+            WebsocketMessageRequest::UpdateItems(msg) => WebsocketMessageResponse::UpdateItems(msg),
+            WebsocketMessageRequest::TogglePurchased(msg) => {
+                WebsocketMessageResponse::TogglePurchased(TogglePurchasedResponse::from(msg))
+            }
+            WebsocketMessageRequest::ClearPurchased(msg) => {
+                WebsocketMessageResponse::ClearPurchased(ClearPurchasedResponse::from(msg))
+            }
             WebsocketMessageRequest::JoinGroup(msg) => WebsocketMessageResponse::JoinGroup(msg),
             WebsocketMessageRequest::ApproveJoin(msg) => WebsocketMessageResponse::ApproveJoin(msg),
+            WebsocketMessageRequest::MarkRead(msg) => {
+                WebsocketMessageResponse::ReadReceipt(ReadReceiptResponse::from(msg))
+            }
+            WebsocketMessageRequest::Typing(msg) => WebsocketMessageResponse::Typing(msg),
         }
     }
 }
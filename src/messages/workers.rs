@@ -1,9 +1,38 @@
-use super::websocket::WebsocketMessageRequest;
+use tokio::sync::oneshot;
+
+use super::websocket::{
+    AddItemsResponse, DirectChatMessageResponse, ErrorCode, GroupChatMessageResponse,
+    GroupUpdateMessage, MemberRemovedMessage, SyncCheckpoint, WebsocketMessageResponse,
+    WebsocketRequest,
+};
+
+pub struct ClientSession {
+    pub session_id: uuid::Uuid,
+    pub device_id: String,
+    pub websocket_session: actix_ws::Session,
+}
+
+// Outcome of a stored request, reported once the database worker flushed it.
+pub struct WriteResult {
+    pub user_id: uuid::Uuid,
+    pub session_id: Option<uuid::Uuid>,
+    pub request_id: Option<String>,
+    pub result: Result<(), String>,
+}
 
 pub enum WorkerMessageRequest {
-    WebsocketMessage(WebsocketMessageRequest),
-    ClientShutdown(uuid::Uuid),
-    ClientLogin(uuid::Uuid, actix_ws::Session),
+    WebsocketMessage(WebsocketRequest),
+    ClientShutdown(uuid::Uuid, uuid::Uuid),
+    ClientLogin(uuid::Uuid, ClientSession),
+    GroupUpdate(GroupUpdateMessage),
+    MemberRemoved(MemberRemovedMessage),
+    ServerShutdown(oneshot::Sender<()>),
+    // Replies with the given users that currently have an open session.
+    OnlineUsers(Vec<uuid::Uuid>, oneshot::Sender<Vec<uuid::Uuid>>),
+    WriteCompleted(WriteResult),
+    // Sends a sync checkpoint to the sessions that were sent something since their last one.
+    SyncCheckpoint,
+    SyncAck(uuid::Uuid, uuid::Uuid, SyncCheckpoint),
 }
 
 impl std::fmt::Debug for WorkerMessageRequest {
@@ -12,12 +41,66 @@ impl std::fmt::Debug for WorkerMessageRequest {
             WorkerMessageRequest::WebsocketMessage(message) => {
                 write!(f, "WorkerMessage::WebsocketMessage({:?})", message)
             }
-            WorkerMessageRequest::ClientShutdown(uuid) => {
-                write!(f, "WorkerMessage::ClientShutdown({})", uuid)
+            WorkerMessageRequest::ClientShutdown(uuid, session_id) => {
+                write!(f, "WorkerMessage::ClientShutdown({}, {})", uuid, session_id)
+            }
+            WorkerMessageRequest::ClientLogin(uuid, session) => {
+                write!(
+                    f,
+                    "WorkerMessage::ClientLogin({}, Session({}, {}))",
+                    uuid, session.session_id, session.device_id
+                )
+            }
+            WorkerMessageRequest::GroupUpdate(message) => {
+                write!(f, "WorkerMessage::GroupUpdate({:?})", message)
             }
-            WorkerMessageRequest::ClientLogin(uuid, _session) => {
-                write!(f, "WorkerMessage::ClientLogin({}, Session)", uuid)
+            WorkerMessageRequest::MemberRemoved(message) => {
+                write!(f, "WorkerMessage::MemberRemoved({:?})", message)
+            }
+            WorkerMessageRequest::ServerShutdown(_) => {
+                write!(f, "WorkerMessage::ServerShutdown")
+            }
+            WorkerMessageRequest::OnlineUsers(user_ids, _) => {
+                write!(f, "WorkerMessage::OnlineUsers({:?})", user_ids)
+            }
+            WorkerMessageRequest::WriteCompleted(write_result) => {
+                write!(
+                    f,
+                    "WorkerMessage::WriteCompleted({}, {:?})",
+                    write_result.user_id, write_result.result
+                )
+            }
+            WorkerMessageRequest::SyncCheckpoint => {
+                write!(f, "WorkerMessage::SyncCheckpoint")
+            }
+            WorkerMessageRequest::SyncAck(uuid, session_id, checkpoint) => {
+                write!(
+                    f,
+                    "WorkerMessage::SyncAck({}, {}, {:?})",
+                    uuid, session_id, checkpoint
+                )
             }
         }
     }
 }
+
+#[derive(Debug)]
+pub enum DatabaseWorkerRequest {
+    // Replies with the result once the response is written to the database.
+    Store(
+        WebsocketMessageResponse,
+        oneshot::Sender<Result<(), String>>,
+    ),
+    MergeItems(
+        AddItemsResponse,
+        oneshot::Sender<Result<AddItemsResponse, (ErrorCode, String)>>,
+    ),
+    Flush(oneshot::Sender<()>),
+    // Chat messages that were broadcast but not flushed yet, used to complete history pages.
+    PendingDirectChat(
+        uuid::Uuid,
+        uuid::Uuid,
+        oneshot::Sender<Vec<DirectChatMessageResponse>>,
+    ),
+    PendingGroupChat(uuid::Uuid, oneshot::Sender<Vec<GroupChatMessageResponse>>),
+}
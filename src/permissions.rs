@@ -0,0 +1,55 @@
+use postgres_types::{FromSql, ToSql};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, ToSql, FromSql, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[postgres(name = "group_role")]
+pub enum GroupRole {
+    #[postgres(name = "owner")]
+    Owner,
+    #[postgres(name = "admin")]
+    Admin,
+    #[postgres(name = "member")]
+    Member,
+    #[postgres(name = "viewer")]
+    Viewer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    ViewGroup,
+    EditItems,
+    PostMessages,
+    ApproveJoins,
+    ManageMembers,
+    ManageGroup,
+    OwnGroup,
+}
+
+impl GroupRole {
+    fn rank(self) -> u8 {
+        match self {
+            GroupRole::Owner => 3,
+            GroupRole::Admin => 2,
+            GroupRole::Member => 1,
+            GroupRole::Viewer => 0,
+        }
+    }
+
+    pub fn allows(self, permission: Permission) -> bool {
+        let required = match permission {
+            Permission::ViewGroup => GroupRole::Viewer,
+            Permission::EditItems | Permission::PostMessages => GroupRole::Member,
+            Permission::ApproveJoins | Permission::ManageMembers | Permission::ManageGroup => {
+                GroupRole::Admin
+            }
+            Permission::OwnGroup => GroupRole::Owner,
+        };
+
+        self.rank() >= required.rank()
+    }
+
+    pub fn outranks(self, other: GroupRole) -> bool {
+        self.rank() > other.rank()
+    }
+}
@@ -5,11 +5,54 @@ pub mod sql_types {
     #[diesel(postgres_type(name = "approval"))]
     pub struct Approval;
 
+    #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "group_role"))]
+    pub struct GroupRole;
+
     #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "product_unit"))]
     pub struct ProductUnit;
 }
 
+diesel::table! {
+    events (id) {
+        id -> Uuid,
+        sequence -> Int4,
+        group_id -> Nullable<Uuid>,
+        recipient_id -> Nullable<Uuid>,
+        payload -> Jsonb,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::GroupRole;
+
+    group_invites (id) {
+        id -> Uuid,
+        group_id -> Uuid,
+        token -> Text,
+        created_by_user -> Uuid,
+        role -> GroupRole,
+        max_uses -> Nullable<Int4>,
+        uses -> Int4,
+        expires_at -> Nullable<Timestamptz>,
+        revoked -> Bool,
+        created_at -> Timestamptz,
+        email -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    group_read_cursors (user_id, group_id) {
+        user_id -> Uuid,
+        group_id -> Uuid,
+        sequence -> Int4,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     group_messages (id) {
         id -> Uuid,
@@ -37,8 +80,23 @@ diesel::table! {
         id -> Uuid,
         product_id -> Uuid,
         group_id -> Uuid,
-        unit -> Nullable<ProductUnit>,
-        quantity -> Nullable<Numeric>,
+        unit -> ProductUnit,
+        quantity -> Numeric,
+        list_id -> Uuid,
+        purchased_by -> Nullable<Uuid>,
+        purchased_at -> Nullable<Timestamptz>,
+        note -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    lists (id) {
+        id -> Uuid,
+        group_id -> Uuid,
+        name -> Text,
+        position -> Int4,
+        archived -> Bool,
+        created_at -> Timestamptz,
     }
 }
 
@@ -63,6 +121,24 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::ProductUnit;
+
+    purchased_items (id) {
+        id -> Uuid,
+        product_id -> Uuid,
+        group_id -> Uuid,
+        list_id -> Uuid,
+        unit -> ProductUnit,
+        quantity -> Numeric,
+        purchased_by -> Nullable<Uuid>,
+        purchased_at -> Timestamptz,
+        cleared_at -> Timestamptz,
+        note -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::Approval;
@@ -71,6 +147,19 @@ diesel::table! {
         user_id -> Uuid,
         group_id -> Uuid,
         approved -> Nullable<Approval>,
+        created_at -> Timestamptz,
+        resolved_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    user_sync_cursors (user_id, device_id) {
+        user_id -> Uuid,
+        direct_sequence -> Int4,
+        group_sequence -> Int4,
+        event_sequence -> Int4,
+        updated_at -> Timestamptz,
+        device_id -> Text,
     }
 }
 
@@ -83,34 +172,56 @@ diesel::table! {
         email -> Text,
         password -> Text,
         image -> Nullable<Text>,
+        last_seen_at -> Nullable<Timestamptz>,
     }
 }
 
 diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::GroupRole;
+
     users_groups (id) {
         id -> Uuid,
         group_id -> Uuid,
         user_id -> Uuid,
+        role -> GroupRole,
     }
 }
 
+diesel::joinable!(events -> groups (group_id));
+diesel::joinable!(events -> users (recipient_id));
+diesel::joinable!(group_invites -> groups (group_id));
+diesel::joinable!(group_invites -> users (created_by_user));
 diesel::joinable!(group_messages -> groups (to_group));
+diesel::joinable!(group_read_cursors -> groups (group_id));
+diesel::joinable!(group_read_cursors -> users (user_id));
 diesel::joinable!(group_messages -> users (sender));
 diesel::joinable!(groups -> users (created_by_user));
 diesel::joinable!(items -> groups (group_id));
 diesel::joinable!(items -> products (product_id));
+diesel::joinable!(items -> users (purchased_by));
+diesel::joinable!(lists -> groups (group_id));
+diesel::joinable!(purchased_items -> products (product_id));
+diesel::joinable!(purchased_items -> users (purchased_by));
 diesel::joinable!(user_group_join_requests -> groups (group_id));
 diesel::joinable!(user_group_join_requests -> users (user_id));
+diesel::joinable!(user_sync_cursors -> users (user_id));
 diesel::joinable!(users_groups -> groups (group_id));
 diesel::joinable!(users_groups -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    events,
+    group_invites,
     group_messages,
+    group_read_cursors,
     groups,
     items,
+    lists,
     messages,
     products,
+    purchased_items,
     user_group_join_requests,
+    user_sync_cursors,
     users,
     users_groups,
 );
@@ -0,0 +1,123 @@
+use postgres_types::{FromSql, ToSql};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+pub type Quantity = Decimal;
+
+// Kept in sync with the quantity limit constraints on items and purchased_items
+pub const MAX_QUANTITY: Quantity = Decimal::from_parts(1_000_000, 0, 0, false, 0);
+pub const MAX_QUANTITY_SCALE: u32 = 3;
+
+pub fn is_valid_quantity(quantity: Quantity) -> bool {
+    quantity > Quantity::ZERO && quantity <= MAX_QUANTITY && quantity.scale() <= MAX_QUANTITY_SCALE
+}
+
+#[derive(Deserialize, Serialize, ToSql, FromSql, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[postgres(name = "product_unit")]
+pub enum ProductUnit {
+    #[postgres(name = "g")]
+    G,
+    #[postgres(name = "kg")]
+    Kg,
+    #[postgres(name = "pc")]
+    Pc,
+    #[postgres(name = "l")]
+    L,
+    #[postgres(name = "ml")]
+    Ml,
+}
+
+impl ProductUnit {
+    fn base_unit(self) -> (ProductUnit, Decimal) {
+        match self {
+            ProductUnit::G => (ProductUnit::G, Decimal::ONE),
+            ProductUnit::Kg => (ProductUnit::G, Decimal::ONE_THOUSAND),
+            ProductUnit::Ml => (ProductUnit::Ml, Decimal::ONE),
+            ProductUnit::L => (ProductUnit::Ml, Decimal::ONE_THOUSAND),
+            ProductUnit::Pc => (ProductUnit::Pc, Decimal::ONE),
+        }
+    }
+}
+
+pub fn are_compatible(from: ProductUnit, to: ProductUnit) -> bool {
+    from.base_unit().0 == to.base_unit().0
+}
+
+pub fn convert(quantity: Quantity, from: ProductUnit, to: ProductUnit) -> Option<Quantity> {
+    let (from_base, from_factor) = from.base_unit();
+    let (to_base, to_factor) = to.base_unit();
+
+    if from_base != to_base {
+        return None;
+    }
+
+    quantity
+        .checked_mul(from_factor)?
+        .checked_div(to_factor)
+        .map(|converted| converted.normalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn units_with_the_same_base_are_compatible() {
+        assert!(are_compatible(ProductUnit::G, ProductUnit::Kg));
+        assert!(are_compatible(ProductUnit::Kg, ProductUnit::G));
+        assert!(are_compatible(ProductUnit::L, ProductUnit::Ml));
+        assert!(are_compatible(ProductUnit::Pc, ProductUnit::Pc));
+    }
+
+    #[test]
+    fn units_with_different_bases_are_not_compatible() {
+        assert!(!are_compatible(ProductUnit::G, ProductUnit::Ml));
+        assert!(!are_compatible(ProductUnit::Kg, ProductUnit::L));
+        assert!(!are_compatible(ProductUnit::Pc, ProductUnit::G));
+    }
+
+    #[test]
+    fn convert_scales_between_units() {
+        assert_eq!(
+            convert(Decimal::new(15, 1), ProductUnit::Kg, ProductUnit::G),
+            Some(Decimal::from(1500))
+        );
+        assert_eq!(
+            convert(Decimal::from(250), ProductUnit::Ml, ProductUnit::L),
+            Some(Decimal::new(25, 2))
+        );
+        assert_eq!(
+            convert(Decimal::from(3), ProductUnit::Pc, ProductUnit::Pc),
+            Some(Decimal::from(3))
+        );
+    }
+
+    #[test]
+    fn convert_normalizes_the_result() {
+        let converted = convert(Decimal::from(2000), ProductUnit::G, ProductUnit::Kg).unwrap();
+        assert_eq!(converted.to_string(), "2");
+    }
+
+    #[test]
+    fn valid_quantities_are_positive_bounded_and_limited_in_scale() {
+        assert!(is_valid_quantity(Decimal::new(1, 3)));
+        assert!(is_valid_quantity(MAX_QUANTITY));
+        assert!(!is_valid_quantity(Decimal::ZERO));
+        assert!(!is_valid_quantity(Decimal::NEGATIVE_ONE));
+        assert!(!is_valid_quantity(MAX_QUANTITY + Decimal::ONE));
+        assert!(!is_valid_quantity(Decimal::new(1, 4)));
+    }
+
+    #[test]
+    fn convert_rejects_incompatible_units() {
+        assert_eq!(convert(Decimal::ONE, ProductUnit::Kg, ProductUnit::L), None);
+        assert_eq!(convert(Decimal::ONE, ProductUnit::Pc, ProductUnit::G), None);
+    }
+
+    #[test]
+    fn convert_returns_none_on_overflow() {
+        assert_eq!(convert(Decimal::MAX, ProductUnit::Kg, ProductUnit::G), None);
+        assert_eq!(convert(Decimal::MAX, ProductUnit::L, ProductUnit::Ml), None);
+    }
+}
@@ -1,39 +1,214 @@
-use deadpool_postgres::Pool;
+use deadpool_postgres::{Client, Pool};
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{mpsc, oneshot, Mutex};
 use tokio::time::{sleep, Duration};
 use tokio_postgres::NoTls;
 
-use crate::db::models::chat_message::DirectChatMessage;
-use crate::db::models::Group;
+use crate::db::models::chat_message::{DirectChatMessage, GroupChatMessage};
+use crate::db::models::{Event, GroupReadCursor, Item};
+use crate::messages::websocket::AddItemResponse;
+use crate::messages::websocket::AddItemsResponse;
+use crate::messages::websocket::ClearPurchasedResponse;
+use crate::messages::websocket::Conversation;
 use crate::messages::websocket::DirectChatMessageResponse;
+use crate::messages::websocket::ErrorCode;
+use crate::messages::websocket::GroupChatMessageResponse;
+use crate::messages::websocket::ReadReceiptResponse;
+use crate::messages::websocket::RemoveItemsMessage;
+use crate::messages::websocket::TogglePurchasedResponse;
+use crate::messages::websocket::UpdateItemMessage;
+use crate::messages::websocket::UpdateItemsMessage;
 use crate::messages::websocket::WebsocketMessageResponse;
+use crate::messages::workers::DatabaseWorkerRequest;
+use crate::units::{self, ProductUnit, Quantity};
+
+pub enum ItemChange {
+    Remove(RemoveItemsMessage),
+    Update(UpdateItemsMessage),
+    TogglePurchased(TogglePurchasedResponse),
+    ClearPurchased(ClearPurchasedResponse),
+}
+
+// Every stored response is one write. Rows keep the id of their write, so a failing row only
+// fails its own write instead of the whole flush.
+pub type WriteId = u64;
 
 pub struct Storage {
-    pub direct_chat_message: Vec<DirectChatMessageResponse>,
+    pub direct_chat_message: Vec<(WriteId, DirectChatMessageResponse)>,
+    pub group_chat_message: Vec<(WriteId, GroupChatMessageResponse)>,
+    pub added_items: Vec<(WriteId, Item)>,
+    pub item_changes: Vec<(WriteId, ItemChange)>,
+    pub read_receipts: Vec<(WriteId, ReadReceiptResponse)>,
+    pub events: Vec<(WriteId, Event)>,
+    pub replies: Vec<(WriteId, oneshot::Sender<Result<(), String>>)>,
+    pub failures: HashMap<WriteId, String>,
+    next_write_id: WriteId,
 }
 
 impl Storage {
     pub fn new() -> Self {
         Storage {
             direct_chat_message: Vec::new(),
+            group_chat_message: Vec::new(),
+            added_items: Vec::new(),
+            item_changes: Vec::new(),
+            read_receipts: Vec::new(),
+            events: Vec::new(),
+            replies: Vec::new(),
+            failures: HashMap::new(),
+            next_write_id: 0,
         }
     }
+
+    fn next_write_id(&mut self) -> WriteId {
+        self.next_write_id += 1;
+        self.next_write_id
+    }
 }
 
-pub fn spawn_database_worker(pool: Pool<NoTls>) -> mpsc::UnboundedSender<WebsocketMessageResponse> {
+pub fn spawn_database_worker(pool: Pool<NoTls>) -> mpsc::UnboundedSender<DatabaseWorkerRequest> {
     let (tx, mut rx) = mpsc::unbounded_channel();
     let storage = Arc::new(Mutex::new(Storage::new()));
     let receiver_storage = storage.clone();
-    let message_pool = pool.clone();
+    let receiver_pool = pool.clone();
 
     tokio::spawn(async move {
-        while let Some(msg) = rx.recv().await {
+        while let Some(request) = rx.recv().await {
+            let (write_id, msg) = match request {
+                DatabaseWorkerRequest::Store(msg, reply) => {
+                    let mut storage = receiver_storage.lock().await;
+                    let write_id = storage.next_write_id();
+                    storage.replies.push((write_id, reply));
+                    (write_id, msg)
+                }
+                DatabaseWorkerRequest::MergeItems(add_items, reply) => {
+                    let mut storage = receiver_storage.lock().await;
+                    let add_items = merge_items(&receiver_pool, &mut storage, add_items).await;
+                    if reply.send(add_items).is_err() {
+                        println!("Merged items receiver dropped");
+                    }
+                    continue;
+                }
+                DatabaseWorkerRequest::Flush(reply) => {
+                    let mut storage = receiver_storage.lock().await;
+                    match receiver_pool.get().await {
+                        Ok(client_connection) => {
+                            flush_storage(&client_connection, &mut storage).await
+                        }
+                        Err(err) => println!("Error obtaining client connection to flush: {}", err),
+                    }
+                    if reply.send(()).is_err() {
+                        println!("Flush receiver dropped");
+                    }
+                    continue;
+                }
+                DatabaseWorkerRequest::PendingDirectChat(user_id, peer_id, reply) => {
+                    let storage = receiver_storage.lock().await;
+                    let pending = storage
+                        .direct_chat_message
+                        .iter()
+                        .map(|(_, message)| message)
+                        .filter(|message| {
+                            (message.sender_id == user_id && message.receiver_id == peer_id)
+                                || (message.sender_id == peer_id && message.receiver_id == user_id)
+                        })
+                        .cloned()
+                        .collect();
+                    if reply.send(pending).is_err() {
+                        println!("Pending direct chat receiver dropped");
+                    }
+                    continue;
+                }
+                DatabaseWorkerRequest::PendingGroupChat(group_id, reply) => {
+                    let storage = receiver_storage.lock().await;
+                    let pending = storage
+                        .group_chat_message
+                        .iter()
+                        .map(|(_, message)| message)
+                        .filter(|message| message.group_id == group_id)
+                        .cloned()
+                        .collect();
+                    if reply.send(pending).is_err() {
+                        println!("Pending group chat receiver dropped");
+                    }
+                    continue;
+                }
+            };
+
+            if let Some(event) = Event::from_response(&msg) {
+                receiver_storage.lock().await.events.push((write_id, event));
+            }
+
             match msg {
                 WebsocketMessageResponse::DirectChatMessage(chat_message) => {
                     let mut storage = receiver_storage.lock().await;
-                    storage.direct_chat_message.push(chat_message);
+                    storage.direct_chat_message.push((write_id, chat_message));
+                }
+                WebsocketMessageResponse::GroupChatMessage(chat_message) => {
+                    let mut storage = receiver_storage.lock().await;
+                    storage.group_chat_message.push((write_id, chat_message));
+                }
+                WebsocketMessageResponse::AddItems(add_items) => {
+                    let mut storage = receiver_storage.lock().await;
+                    let (merged, added): (Vec<_>, Vec<_>) =
+                        add_items.items.into_iter().partition(|item| item.merged);
+
+                    storage
+                        .added_items
+                        .extend(added.into_iter().map(|item| (write_id, Item::from(item))));
+
+                    if !merged.is_empty() {
+                        storage.item_changes.push((
+                            write_id,
+                            ItemChange::Update(UpdateItemsMessage {
+                                sender_id: add_items.sender_id,
+                                group_id: add_items.group_id,
+                                list_id: add_items.list_id,
+                                items: merged
+                                    .into_iter()
+                                    .map(|item| UpdateItemMessage {
+                                        item_id: item.id,
+                                        product_unit: Some(item.product_unit),
+                                        quantity: Some(item.quantity),
+                                        note: None,
+                                    })
+                                    .collect(),
+                            }),
+                        ));
+                    }
+                }
+                WebsocketMessageResponse::RemoveItems(remove_items) => {
+                    let mut storage = receiver_storage.lock().await;
+                    storage
+                        .item_changes
+                        .push((write_id, ItemChange::Remove(remove_items)));
+                }
+                WebsocketMessageResponse::UpdateItems(update_items) => {
+                    let mut storage = receiver_storage.lock().await;
+                    storage
+                        .item_changes
+                        .push((write_id, ItemChange::Update(update_items)));
+                }
+                WebsocketMessageResponse::TogglePurchased(toggle_purchased) => {
+                    let mut storage = receiver_storage.lock().await;
+                    storage
+                        .item_changes
+                        .push((write_id, ItemChange::TogglePurchased(toggle_purchased)));
+                }
+                WebsocketMessageResponse::ClearPurchased(clear_purchased) => {
+                    let mut storage = receiver_storage.lock().await;
+                    storage
+                        .item_changes
+                        .push((write_id, ItemChange::ClearPurchased(clear_purchased)));
                 }
+                WebsocketMessageResponse::ReadReceipt(read_receipt) => {
+                    let mut storage = receiver_storage.lock().await;
+                    storage.read_receipts.push((write_id, read_receipt));
+                }
+                // Join notifications are only persisted as events.
+                WebsocketMessageResponse::JoinGroup(_)
+                | WebsocketMessageResponse::ApproveJoin(_) => {}
                 _ => {
                     println!("unhandled message received")
                 }
@@ -52,22 +227,346 @@ pub fn spawn_database_worker(pool: Pool<NoTls>) -> mpsc::UnboundedSender<Websock
                 continue;
             };
             let mut storage = storage.lock().await;
+            flush_storage(&client_connection, &mut storage).await;
+        }
+    });
+
+    tx
+}
+
+async fn flush_storage(client: &Client<NoTls>, storage: &mut Storage) {
+    let direct_chat_messages = storage
+        .direct_chat_message
+        .drain(..)
+        .map(|(write_id, message)| (write_id, DirectChatMessage::from(message)))
+        .collect();
+    insert_isolated(client, &mut storage.failures, direct_chat_messages).await;
+
+    let group_chat_messages = storage
+        .group_chat_message
+        .drain(..)
+        .map(|(write_id, message)| (write_id, GroupChatMessage::from(message)))
+        .collect();
+    insert_isolated(client, &mut storage.failures, group_chat_messages).await;
+
+    // Applied after the chat messages are inserted, so receipts cover messages of this flush.
+    for (write_id, read_receipt) in storage.read_receipts.drain(..) {
+        if let Err(err) = apply_read_receipt(client, read_receipt).await {
+            record_failure(
+                &mut storage.failures,
+                write_id,
+                "applying read receipt",
+                err,
+            );
+        }
+    }
+
+    flush_items(client, storage).await;
+
+    // Events of failed writes are dropped, they would replay changes that were never stored.
+    // Events are inserted one by one anyway, so they are not batched across writes.
+    let events = std::mem::take(&mut storage.events);
+    for (write_id, event) in events {
+        if storage.failures.contains_key(&write_id) {
+            continue;
+        }
+        if let Err(err) = Event::insert_bulk(client, std::slice::from_ref(&event)).await {
+            record_failure(&mut storage.failures, write_id, "inserting event", err);
+        }
+    }
+
+    for (write_id, reply) in storage.replies.drain(..) {
+        let result = match storage.failures.remove(&write_id) {
+            Some(err) => Err(err),
+            None => Ok(()),
+        };
+        // The sender is gone when nobody waits for the result anymore.
+        let _ = reply.send(result);
+    }
+    storage.failures.clear();
+}
+
+// Inserts all rows with one statement. When that fails, the rows are inserted again per write,
+// so a bad row only fails the write it belongs to. Rows of one write are stored next to each
+// other, since a write is pushed at once.
+async fn insert_isolated<T: BulkInsert>(
+    client: &Client<NoTls>,
+    failures: &mut HashMap<WriteId, String>,
+    rows: Vec<(WriteId, T)>,
+) {
+    let (write_ids, rows): (Vec<WriteId>, Vec<T>) = rows.into_iter().unzip();
+    if rows.is_empty() {
+        return;
+    }
+
+    if let Err(err) = T::insert_bulk(client, &rows).await {
+        println!("Error inserting batch, retrying per write: {:?}", err);
+
+        let mut start = 0;
+        for write in write_ids.chunk_by(|a, b| a == b) {
+            let end = start + write.len();
+            if let Err(err) = T::insert_bulk(client, &rows[start..end]).await {
+                record_failure(failures, write[0], "inserting rows", err);
+            }
+            start = end;
+        }
+    }
+}
+
+trait BulkInsert: Sized {
+    async fn insert_bulk(
+        client: &Client<NoTls>,
+        rows: &[Self],
+    ) -> Result<(), tokio_postgres::Error>;
+}
 
-            if let Err(err) = DirectChatMessage::insert_bulk(
-                &client_connection,
-                storage
-                    .direct_chat_message
-                    .drain(..)
-                    .map(DirectChatMessage::from)
-                    .collect::<Vec<DirectChatMessage>>()
-                    .as_slice(),
+impl BulkInsert for DirectChatMessage {
+    async fn insert_bulk(
+        client: &Client<NoTls>,
+        rows: &[Self],
+    ) -> Result<(), tokio_postgres::Error> {
+        DirectChatMessage::insert_bulk(client, rows).await
+    }
+}
+
+impl BulkInsert for GroupChatMessage {
+    async fn insert_bulk(
+        client: &Client<NoTls>,
+        rows: &[Self],
+    ) -> Result<(), tokio_postgres::Error> {
+        GroupChatMessage::insert_bulk(client, rows).await
+    }
+}
+
+impl BulkInsert for Item {
+    async fn insert_bulk(
+        client: &Client<NoTls>,
+        rows: &[Self],
+    ) -> Result<(), tokio_postgres::Error> {
+        Item::insert_bulk(client, rows).await
+    }
+}
+
+fn record_failure(
+    failures: &mut HashMap<WriteId, String>,
+    write_id: WriteId,
+    action: &str,
+    err: tokio_postgres::Error,
+) {
+    println!("Error {} of write {}: {:?}", action, write_id, err);
+    failures
+        .entry(write_id)
+        .or_insert_with(|| format!("Error {}: {}", action, err));
+}
+
+async fn apply_read_receipt(
+    client: &Client<NoTls>,
+    read_receipt: ReadReceiptResponse,
+) -> Result<(), tokio_postgres::Error> {
+    match read_receipt.conversation {
+        Conversation::Direct { peer_id } => {
+            DirectChatMessage::mark_read(
+                client,
+                &read_receipt.reader_id,
+                &peer_id,
+                read_receipt.up_to_sequence,
+            )
+            .await?;
+        }
+        Conversation::Group { group_id } => {
+            GroupReadCursor {
+                user_id: read_receipt.reader_id,
+                group_id,
+                sequence: read_receipt.up_to_sequence,
+            }
+            .advance(client)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn flush_items(client: &Client<NoTls>, storage: &mut Storage) {
+    // Items have to be inserted before changes are applied, otherwise a change to an item
+    // added within the same flush interval would find no row to update. Changes are
+    // applied in the order they were received.
+    let added_items = std::mem::take(&mut storage.added_items);
+    insert_isolated(client, &mut storage.failures, added_items).await;
+
+    for (write_id, item_change) in storage.item_changes.drain(..) {
+        if let Err(err) = apply_item_change(client, item_change).await {
+            record_failure(&mut storage.failures, write_id, "applying item change", err);
+        }
+    }
+}
+
+// Pending item changes are flushed first so the lookup sees every change that was broadcast
+// before this request. Items are merged into an unpurchased item of the same product with a
+// compatible unit, duplicates within the request are merged with each other.
+async fn merge_items(
+    pool: &Pool<NoTls>,
+    storage: &mut Storage,
+    mut add_items: AddItemsResponse,
+) -> Result<AddItemsResponse, (ErrorCode, String)> {
+    let client_connection = pool
+        .get()
+        .await
+        .map_err(|err| (ErrorCode::DatabaseError, err.to_string()))?;
+
+    flush_items(&client_connection, storage).await;
+
+    let product_ids = add_items
+        .items
+        .iter()
+        .map(|item| item.product_id)
+        .collect::<Vec<uuid::Uuid>>();
+
+    let mut existing_items = match Item::get_unpurchased_by_products(
+        &add_items.group_id,
+        &add_items.list_id,
+        &product_ids,
+        &client_connection,
+    )
+    .await
+    {
+        Ok(existing_items) => existing_items,
+        Err(err) => {
+            println!("Error loading items to merge: {:?}", err);
+            return Err((ErrorCode::DatabaseError, err.to_string()));
+        }
+    };
+
+    let mut merged_items: Vec<AddItemResponse> = Vec::new();
+    for mut item in add_items.items.drain(..) {
+        if let Some(target) = merged_items.iter_mut().find(|target| {
+            target.product_id == item.product_id
+                && units::are_compatible(item.product_unit, target.product_unit)
+        }) {
+            target.quantity = merge_quantity(target.quantity, target.product_unit, &item)?;
+            continue;
+        }
+
+        if let Some(position) = existing_items.iter().position(|existing| {
+            existing.product_id == item.product_id
+                && units::are_compatible(item.product_unit, existing.unit)
+        }) {
+            let existing = existing_items.remove(position);
+            item.quantity = merge_quantity(existing.quantity, existing.unit, &item)?;
+            item.id = existing.id;
+            item.product_unit = existing.unit;
+            item.merged = true;
+        }
+
+        merged_items.push(item);
+    }
+
+    add_items.items = merged_items;
+    Ok(add_items)
+}
+
+// Rejects the request instead of merging past MAX_QUANTITY, the constraint on items would fail
+// the whole write otherwise.
+fn merge_quantity(
+    quantity: Quantity,
+    unit: ProductUnit,
+    item: &AddItemResponse,
+) -> Result<Quantity, (ErrorCode, String)> {
+    units::convert(item.quantity, item.product_unit, unit)
+        .and_then(|added| quantity.checked_add(added))
+        .map(|merged| merged.round_dp(units::MAX_QUANTITY_SCALE))
+        .filter(|merged| *merged <= units::MAX_QUANTITY)
+        .ok_or_else(|| {
+            (
+                ErrorCode::InvalidRequest,
+                format!(
+                    "Merged quantity would exceed the maximum of {}",
+                    units::MAX_QUANTITY
+                ),
+            )
+        })
+}
+
+async fn apply_item_change(
+    client: &Client<NoTls>,
+    item_change: ItemChange,
+) -> Result<u64, tokio_postgres::Error> {
+    match item_change {
+        ItemChange::Remove(remove_items) => {
+            Item::delete_bulk(
+                client,
+                &remove_items.group_id,
+                &remove_items.list_id,
+                &remove_items.items,
             )
             .await
-            {
-                println!("Error inserting direct chat messages: {:?}", err);
+        }
+        ItemChange::Update(update_items) => {
+            let mut rows_affected = 0;
+            for item in update_items.items.iter() {
+                rows_affected +=
+                    Item::update(client, &update_items.group_id, &update_items.list_id, item)
+                        .await?;
             }
+            Ok(rows_affected)
         }
-    });
+        ItemChange::TogglePurchased(toggle_purchased) => {
+            Item::set_purchased(
+                client,
+                &toggle_purchased.group_id,
+                &toggle_purchased.list_id,
+                &toggle_purchased.item_id,
+                toggle_purchased.purchased_by.as_ref(),
+                toggle_purchased.purchased_at.as_ref(),
+            )
+            .await
+        }
+        ItemChange::ClearPurchased(clear_purchased) => {
+            Item::clear_purchased(
+                client,
+                &clear_purchased.group_id,
+                &clear_purchased.list_id,
+                &clear_purchased.cleared_at,
+            )
+            .await
+        }
+    }
+}
 
-    tx
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add_item(quantity: Quantity, product_unit: ProductUnit) -> AddItemResponse {
+        AddItemResponse {
+            id: uuid::Uuid::new_v4(),
+            product_id: uuid::Uuid::new_v4(),
+            group_id: uuid::Uuid::new_v4(),
+            list_id: uuid::Uuid::new_v4(),
+            product_unit,
+            quantity,
+            merged: false,
+        }
+    }
+
+    #[test]
+    fn merge_quantity_adds_converted_quantity() {
+        let item = add_item(Quantity::from(500), ProductUnit::G);
+        let merged = merge_quantity(Quantity::ONE, ProductUnit::Kg, &item).unwrap();
+        assert_eq!(merged, Quantity::new(15, 1));
+    }
+
+    #[test]
+    fn merge_quantity_rejects_quantities_above_the_maximum() {
+        let item = add_item(units::MAX_QUANTITY, ProductUnit::Kg);
+        let err = merge_quantity(Quantity::ONE, ProductUnit::Kg, &item).unwrap_err();
+        assert!(matches!(err.0, ErrorCode::InvalidRequest));
+    }
+
+    #[test]
+    fn merge_quantity_rejects_overflow() {
+        let item = add_item(Quantity::MAX, ProductUnit::Kg);
+        let err = merge_quantity(Quantity::ONE, ProductUnit::G, &item).unwrap_err();
+        assert!(matches!(err.0, ErrorCode::InvalidRequest));
+    }
 }
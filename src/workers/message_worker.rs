@@ -1,41 +1,194 @@
+use actix_ws::{CloseCode, CloseReason};
+use chrono::Utc;
 use deadpool_postgres::Pool;
-use serde::Serialize;
-use std::collections::HashMap;
-use tokio::sync::mpsc;
+use std::collections::{HashMap, HashSet, VecDeque};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{sleep, Duration};
 use tokio_postgres::NoTls;
 
+use crate::constants;
 use crate::db;
 use crate::db::models;
+use crate::db::models::chat_message::{DirectChatMessage, GroupChatMessage};
 use crate::messages::websocket::{
-    ApproveJoin, DirectChatMessageResponse, GroupId, WebsocketMessage, WebsocketMessageResponse,
+    AckResponse, AddItemsResponse, Conversation, DirectChatMessageResponse, ErrorCode,
+    ErrorResponse, GroupChatMessageResponse, GroupId, GroupUpdate, MarkReadRequest,
+    PresenceMessage, PresenceStatus, SyncCheckpoint, TypingMessage, UnreadCountResponse,
+    WebsocketMessage, WebsocketMessageRequest, WebsocketMessageResponse, WebsocketRequest,
 };
-use crate::messages::workers::WorkerMessageRequest;
+use crate::messages::workers::{
+    ClientSession, DatabaseWorkerRequest, WorkerMessageRequest, WriteResult,
+};
+use crate::permissions::{GroupRole, Permission};
 
 pub struct ActiveUser {
-    pub groups: Vec<uuid::Uuid>,
-    pub websocket_session: actix_ws::Session,
+    pub groups: HashMap<uuid::Uuid, GroupRole>,
+    pub sessions: HashMap<uuid::Uuid, ClientSession>,
+    // Sessions that failed a send. They get nothing more, but stay registered until their
+    // connection shuts down, so the user only goes offline with their last session.
+    pub failed_sessions: HashSet<uuid::Uuid>,
+    pub sync: HashMap<uuid::Uuid, SessionSync>,
+}
+
+// Checkpoint progress of a session. Its sync cursor only moves to checkpoints the client
+// acknowledged, whatever was sent after them is replayed on the next login of the device.
+#[derive(Default)]
+pub struct SessionSync {
+    // Something was sent since the last checkpoint.
+    pub pending: bool,
+    pub checkpoint: Option<SyncCheckpoint>,
+    pub acknowledged: Option<SyncCheckpoint>,
+}
+
+const SEQUENCE_BLOCK_SIZE: i64 = 100;
+
+// Chat sequences are reserved from the database in blocks, so most messages are sent without
+// waiting on it. This worker is the only one assigning them, so they still grow with every
+// message. Whatever is left of a block on restart only leaves a gap.
+#[derive(Default)]
+struct SequenceBlocks {
+    direct: VecDeque<i32>,
+    group: VecDeque<i32>,
+}
+
+impl SequenceBlocks {
+    async fn next_direct(&mut self, pool: &Pool<NoTls>) -> Result<i32, (ErrorCode, String)> {
+        if self.direct.is_empty() {
+            let client = pool.get().await.map_err(database_error)?;
+            let reserved = DirectChatMessage::reserve_sequences(&client, SEQUENCE_BLOCK_SIZE)
+                .await
+                .map_err(database_error)?;
+            self.direct.extend(reserved);
+        }
+
+        self.direct
+            .pop_front()
+            .ok_or_else(|| database_error("No direct chat sequence reserved"))
+    }
+
+    async fn next_group(&mut self, pool: &Pool<NoTls>) -> Result<i32, (ErrorCode, String)> {
+        if self.group.is_empty() {
+            let client = pool.get().await.map_err(database_error)?;
+            let reserved = GroupChatMessage::reserve_sequences(&client, SEQUENCE_BLOCK_SIZE)
+                .await
+                .map_err(database_error)?;
+            self.group.extend(reserved);
+        }
+
+        self.group
+            .pop_front()
+            .ok_or_else(|| database_error("No group chat sequence reserved"))
+    }
 }
 
 pub fn spawn_message_worker(
-    database_sender: mpsc::UnboundedSender<WebsocketMessageResponse>,
+    database_sender: mpsc::UnboundedSender<DatabaseWorkerRequest>,
     pool: Pool<NoTls>,
 ) -> mpsc::UnboundedSender<WorkerMessageRequest> {
     let (tx, mut rx) = mpsc::unbounded_channel::<WorkerMessageRequest>();
     let mut user_state: HashMap<uuid::Uuid, ActiveUser> = HashMap::new();
+    let mut sequences = SequenceBlocks::default();
+    let worker_sender = tx.clone();
+
+    let checkpoint_sender = tx.clone();
+    tokio::spawn(async move {
+        loop {
+            sleep(Duration::from_secs(
+                constants::SYNC_CHECKPOINT_INTERVAL_SECONDS,
+            ))
+            .await;
+            if checkpoint_sender
+                .send(WorkerMessageRequest::SyncCheckpoint)
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
 
     tokio::spawn(async move {
         while let Some(msg) = rx.recv().await {
             match msg {
-                WorkerMessageRequest::WebsocketMessage(websocket_message) => {
-                    let websocket_response_message =
-                        WebsocketMessageResponse::from(websocket_message);
+                WorkerMessageRequest::WebsocketMessage(websocket_request) => {
+                    let WebsocketRequest {
+                        request_id,
+                        message: websocket_message,
+                        session_id,
+                    } = websocket_request;
+                    let sender_id = websocket_message.sender_id();
+
+                    if let Err((code, message)) =
+                        authorize_request(&user_state, &pool, &websocket_message).await
+                    {
+                        println!("Rejected websocket request from {}: {}", sender_id, message);
+                        let error = ErrorResponse {
+                            request_id,
+                            code,
+                            message,
+                        };
+                        reply(&mut user_state, &sender_id, session_id, &error.into()).await;
+                        continue;
+                    }
+
+                    let websocket_response_message = match prepare_response(
+                        &database_sender,
+                        &pool,
+                        &mut sequences,
+                        WebsocketMessageResponse::from(websocket_message),
+                    )
+                    .await
+                    {
+                        Ok(websocket_response_message) => websocket_response_message,
+                        Err((code, message)) => {
+                            let error = ErrorResponse {
+                                request_id,
+                                code,
+                                message,
+                            };
+                            reply(&mut user_state, &sender_id, session_id, &error.into()).await;
+                            continue;
+                        }
+                    };
 
                     match &websocket_response_message {
                         WebsocketMessageResponse::DirectChatMessage(chat_message) => {
-                            send_direct_chat_message(&mut user_state, chat_message).await
+                            send_direct_chat_message(&mut user_state, chat_message).await;
+
+                            let unread_count = UnreadCountResponse {
+                                conversation: Conversation::Direct {
+                                    peer_id: chat_message.sender_id,
+                                },
+                                unread_delta: 1,
+                                sequence: chat_message.sequence,
+                            };
+                            send_to_user(
+                                &mut user_state,
+                                &chat_message.receiver_id,
+                                &unread_count.into(),
+                            )
+                            .await;
                         }
                         WebsocketMessageResponse::GroupChatMessage(group_chat_message) => {
                             send_group_message(&mut user_state, group_chat_message).await;
+
+                            let unread_count = UnreadCountResponse {
+                                conversation: Conversation::Group {
+                                    group_id: group_chat_message.group_id,
+                                },
+                                unread_delta: 1,
+                                sequence: group_chat_message.sequence,
+                            };
+                            send_to_users(
+                                &mut user_state,
+                                |user_id, active_user| {
+                                    *user_id != group_chat_message.sender_id
+                                        && active_user
+                                            .groups
+                                            .contains_key(&group_chat_message.group_id)
+                                },
+                                &unread_count.into(),
+                            )
+                            .await;
                         }
                         WebsocketMessageResponse::AddItems(add_items_response) => {
                             send_group_message(&mut user_state, add_items_response).await;
@@ -43,52 +196,261 @@ pub fn spawn_message_worker(
                         WebsocketMessageResponse::RemoveItems(remove_items) => {
                             send_group_message(&mut user_state, remove_items).await;
                         }
+                        WebsocketMessageResponse::UpdateItems(update_items) => {
+                            send_group_message(&mut user_state, update_items).await;
+                        }
+                        WebsocketMessageResponse::TogglePurchased(toggle_purchased) => {
+                            send_group_message(&mut user_state, toggle_purchased).await;
+                        }
+                        WebsocketMessageResponse::ClearPurchased(clear_purchased) => {
+                            send_group_message(&mut user_state, clear_purchased).await;
+                        }
                         WebsocketMessageResponse::JoinGroup(join_group) => {
-                            if let Some(active_user) =
-                                user_state.get_mut(&join_group.group_owner_id)
-                            {
-                                let websocket_message: WebsocketMessage = join_group.clone().into();
-                                if send_message(&websocket_message, active_user).await.is_err() {
-                                    user_state.remove(&join_group.sender_id);
-                                }
-                            }
+                            send_to_users(
+                                &mut user_state,
+                                |_, active_user| {
+                                    active_user
+                                        .groups
+                                        .get(&join_group.group_id)
+                                        .is_some_and(|role| role.allows(Permission::ApproveJoins))
+                                },
+                                &join_group.clone().into(),
+                            )
+                            .await;
                         }
                         WebsocketMessageResponse::ApproveJoin(approve_join) => {
-                            if !is_approver_valid(&pool, approve_join).await {
-                                return;
-                            };
+                            if approve_join.approved {
+                                set_cached_role(
+                                    &mut user_state,
+                                    &approve_join.candidate_id,
+                                    &approve_join.group_id,
+                                    GroupRole::Member,
+                                );
+                            }
 
-                            if let Some(candidate_active_user) =
-                                user_state.get_mut(&approve_join.candidate_id)
-                            {
-                                if send_message(&approve_join.clone().into(), candidate_active_user)
-                                    .await
-                                    .is_err()
-                                {
-                                    user_state.remove(&approve_join.candidate_id);
-                                    continue;
+                            send_to_user(
+                                &mut user_state,
+                                &approve_join.candidate_id,
+                                &approve_join.clone().into(),
+                            )
+                            .await;
+                        }
+                        WebsocketMessageResponse::ReadReceipt(read_receipt) => {
+                            match read_receipt.conversation {
+                                Conversation::Direct { peer_id } => {
+                                    send_to_user(
+                                        &mut user_state,
+                                        &peer_id,
+                                        &read_receipt.clone().into(),
+                                    )
+                                    .await;
                                 }
-
-                                if approve_join.approved {
-                                    candidate_active_user.groups.push(approve_join.group_id)
+                                Conversation::Group { group_id } => {
+                                    send_to_users(
+                                        &mut user_state,
+                                        |_, active_user| active_user.groups.contains_key(&group_id),
+                                        &read_receipt.clone().into(),
+                                    )
+                                    .await;
                                 }
                             }
                         }
+                        WebsocketMessageResponse::GroupUpdate(_) => {}
+                        WebsocketMessageResponse::Typing(typing) => match typing.conversation {
+                            Conversation::Direct { peer_id } => {
+                                send_to_user(&mut user_state, &peer_id, &typing.clone().into())
+                                    .await;
+                            }
+                            Conversation::Group { group_id } => {
+                                send_to_users(
+                                    &mut user_state,
+                                    |user_id, active_user| {
+                                        *user_id != typing.sender_id
+                                            && active_user.groups.contains_key(&group_id)
+                                    },
+                                    &typing.clone().into(),
+                                )
+                                .await;
+                            }
+                        },
+                        WebsocketMessageResponse::UnreadCount(_) => {}
+                        WebsocketMessageResponse::Presence(_) => {}
+                        WebsocketMessageResponse::MemberRemoved(_) => {}
+                        WebsocketMessageResponse::Ack(_) => {}
+                        WebsocketMessageResponse::Error(_) => {}
                     }
+                    // Stored requests are acknowledged once the write completed.
                     if websocket_response_message.delayed_send() {
-                        database_sender
-                            .send(websocket_response_message)
-                            .expect("Failed to send message to database worker");
+                        store_response(
+                            &database_sender,
+                            &worker_sender,
+                            websocket_response_message,
+                            WriteResult {
+                                user_id: sender_id,
+                                session_id,
+                                request_id,
+                                result: Ok(()),
+                            },
+                        );
+                    } else if let Some(request_id) = request_id {
+                        reply(
+                            &mut user_state,
+                            &sender_id,
+                            session_id,
+                            &AckResponse { request_id }.into(),
+                        )
+                        .await;
                     }
                 }
-                WorkerMessageRequest::ClientShutdown(id) => {
-                    user_state.remove(&id);
-                    println!("Shutdown received for ID: {}", id);
+                WorkerMessageRequest::ClientShutdown(id, session_id) => {
+                    // Nothing to do for sessions that never registered or were already drained
+                    // by the server shutdown.
+                    let Some((session, sync)) = remove_session(&mut user_state, &id, &session_id)
+                    else {
+                        println!("Shutdown received for unknown session: {}", session_id);
+                        continue;
+                    };
+
+                    // Anything after the acknowledged checkpoint may not have reached the
+                    // client, it is replayed on the next login.
+                    if let Some(acknowledged) = sync.acknowledged {
+                        advance_sync_cursor(&pool, &id, &session.device_id, acknowledged).await;
+                    }
+
+                    if !user_state.contains_key(&id) {
+                        let last_seen = Utc::now();
+                        store_last_seen(&pool, &id, &last_seen).await;
+                        let presence = PresenceMessage {
+                            user_id: id,
+                            status: PresenceStatus::Offline,
+                            last_seen: Some(last_seen),
+                        };
+                        broadcast_presence(&mut user_state, &pool, &presence).await;
+                    }
+                    println!("Shutdown received for ID: {} session: {}", id, session_id);
                 }
                 WorkerMessageRequest::ClientLogin(id, session) => {
-                    insert_active_user_to_user_state(&mut user_state, id, session.clone(), &pool)
+                    let session_id = session.session_id;
+                    let device_id = session.device_id.clone();
+                    let was_online = user_state.contains_key(&id);
+                    insert_active_user_to_user_state(&mut user_state, id, session, &pool).await;
+                    if !was_online && user_state.contains_key(&id) {
+                        let presence = PresenceMessage {
+                            user_id: id,
+                            status: PresenceStatus::Online,
+                            last_seen: None,
+                        };
+                        broadcast_presence(&mut user_state, &pool, &presence).await;
+                    }
+                    flush_database_worker(&database_sender).await;
+                    replay_missed_messages(&mut user_state, &pool, &id, &session_id, &device_id)
                         .await;
                 }
+                WorkerMessageRequest::GroupUpdate(group_update) => {
+                    // A joining member is cached first, so they get notified too.
+                    if let GroupUpdate::MemberJoined { user_id, role } = group_update.update {
+                        set_cached_role(&mut user_state, &user_id, &group_update.group_id, role);
+                    }
+
+                    send_group_message(&mut user_state, &group_update).await;
+
+                    match group_update.update {
+                        GroupUpdate::Renamed { .. } | GroupUpdate::MemberJoined { .. } => {}
+                        GroupUpdate::OwnerChanged { owner_id } => {
+                            set_cached_role(
+                                &mut user_state,
+                                &group_update.sender_id,
+                                &group_update.group_id,
+                                GroupRole::Admin,
+                            );
+                            set_cached_role(
+                                &mut user_state,
+                                &owner_id,
+                                &group_update.group_id,
+                                GroupRole::Owner,
+                            );
+                        }
+                        GroupUpdate::MemberRoleChanged { user_id, role } => {
+                            set_cached_role(
+                                &mut user_state,
+                                &user_id,
+                                &group_update.group_id,
+                                role,
+                            );
+                        }
+                        GroupUpdate::Deleted => {
+                            for active_user in user_state.values_mut() {
+                                active_user.groups.remove(&group_update.group_id);
+                            }
+                        }
+                    }
+                }
+                WorkerMessageRequest::MemberRemoved(member_removed) => {
+                    // The removed member is still in the group cache, so they get notified too.
+                    send_group_message(&mut user_state, &member_removed).await;
+
+                    if let Some(active_user) = user_state.get_mut(&member_removed.user_id) {
+                        active_user.groups.remove(&member_removed.group_id);
+                    }
+                }
+                WorkerMessageRequest::ServerShutdown(reply_sender) => {
+                    flush_database_worker(&database_sender).await;
+                    let last_seen = Utc::now();
+                    for (id, active_user) in user_state.drain() {
+                        store_last_seen(&pool, &id, &last_seen).await;
+                        for (session_id, session) in active_user.sessions {
+                            let acknowledged = active_user
+                                .sync
+                                .get(&session_id)
+                                .and_then(|sync| sync.acknowledged);
+                            if let Some(acknowledged) = acknowledged {
+                                advance_sync_cursor(&pool, &id, &session.device_id, acknowledged)
+                                    .await;
+                            }
+                            let reason = CloseReason {
+                                code: CloseCode::Away,
+                                description: Some("Server shutdown".to_string()),
+                            };
+                            let _ = session.websocket_session.close(Some(reason)).await;
+                        }
+                    }
+
+                    let _ = reply_sender.send(());
+                }
+                WorkerMessageRequest::OnlineUsers(user_ids, reply_sender) => {
+                    let online = user_ids
+                        .into_iter()
+                        .filter(|user_id| user_state.contains_key(user_id))
+                        .collect();
+                    if reply_sender.send(online).is_err() {
+                        println!("Online users receiver dropped");
+                    }
+                }
+                WorkerMessageRequest::WriteCompleted(write_result) => {
+                    let response = match (write_result.result, write_result.request_id) {
+                        (Ok(()), Some(request_id)) => AckResponse { request_id }.into(),
+                        (Ok(()), None) => continue,
+                        (Err(message), request_id) => ErrorResponse {
+                            request_id,
+                            code: ErrorCode::DatabaseError,
+                            message,
+                        }
+                        .into(),
+                    };
+                    reply(
+                        &mut user_state,
+                        &write_result.user_id,
+                        write_result.session_id,
+                        &response,
+                    )
+                    .await;
+                }
+                WorkerMessageRequest::SyncCheckpoint => {
+                    send_sync_checkpoints(&mut user_state, &pool).await;
+                }
+                WorkerMessageRequest::SyncAck(id, session_id, checkpoint) => {
+                    acknowledge_checkpoint(&mut user_state, &id, &session_id, checkpoint);
+                }
             }
         }
     });
@@ -96,75 +458,558 @@ pub fn spawn_message_worker(
     tx
 }
 
-async fn send_message(
-    message: &WebsocketMessage,
-    user: &mut ActiveUser,
-) -> Result<(), actix_ws::Closed> {
-    user.websocket_session
-        .text(serde_json::to_string(message).expect("Failed to serialize websocket message"))
+async fn merge_items(
+    database_sender: &mpsc::UnboundedSender<DatabaseWorkerRequest>,
+    add_items: AddItemsResponse,
+) -> Result<AddItemsResponse, (ErrorCode, String)> {
+    let (reply_sender, reply_receiver) = oneshot::channel();
+    database_sender
+        .send(DatabaseWorkerRequest::MergeItems(add_items, reply_sender))
+        .expect("Failed to send message to database worker");
+
+    reply_receiver.await.unwrap_or_else(|_| {
+        Err((
+            ErrorCode::DatabaseError,
+            "Database worker dropped merge items reply".to_string(),
+        ))
+    })
+}
+
+// Fills in what the broadcast needs from the database: merged items and the sequence of chat
+// messages, which are only inserted later by the database worker.
+async fn prepare_response(
+    database_sender: &mpsc::UnboundedSender<DatabaseWorkerRequest>,
+    pool: &Pool<NoTls>,
+    sequences: &mut SequenceBlocks,
+    response: WebsocketMessageResponse,
+) -> Result<WebsocketMessageResponse, (ErrorCode, String)> {
+    match response {
+        WebsocketMessageResponse::AddItems(add_items) => {
+            validate_products(pool, &add_items).await?;
+            merge_items(database_sender, add_items)
+                .await
+                .map(WebsocketMessageResponse::AddItems)
+        }
+        WebsocketMessageResponse::DirectChatMessage(mut chat_message) => {
+            chat_message.sequence = sequences.next_direct(pool).await?;
+            Ok(WebsocketMessageResponse::DirectChatMessage(chat_message))
+        }
+        WebsocketMessageResponse::GroupChatMessage(mut chat_message) => {
+            chat_message.sequence = sequences.next_group(pool).await?;
+            Ok(WebsocketMessageResponse::GroupChatMessage(chat_message))
+        }
+        response => Ok(response),
+    }
+}
+
+// Unknown products would fail the insert of the whole flush, so they are rejected up front.
+async fn validate_products(
+    pool: &Pool<NoTls>,
+    add_items: &AddItemsResponse,
+) -> Result<(), (ErrorCode, String)> {
+    let product_ids = add_items
+        .items
+        .iter()
+        .map(|item| item.product_id)
+        .collect::<Vec<uuid::Uuid>>();
+
+    let client = pool.get().await.map_err(database_error)?;
+    let existing_ids = models::Product::get_existing_ids(&product_ids, &client)
         .await
+        .map_err(database_error)?;
+
+    if let Some(product_id) = product_ids
+        .iter()
+        .find(|product_id| !existing_ids.contains(product_id))
+    {
+        return Err((
+            ErrorCode::InvalidRequest,
+            format!("Product {} does not exist", product_id),
+        ));
+    }
+
+    Ok(())
+}
+
+// Hands the response to the database worker. The outcome comes back to this worker as
+// WriteCompleted, so a failed write is reported to the session that sent the request.
+fn store_response(
+    database_sender: &mpsc::UnboundedSender<DatabaseWorkerRequest>,
+    worker_sender: &mpsc::UnboundedSender<WorkerMessageRequest>,
+    response: WebsocketMessageResponse,
+    mut write_result: WriteResult,
+) {
+    let (reply_sender, reply_receiver) = oneshot::channel();
+    database_sender
+        .send(DatabaseWorkerRequest::Store(response, reply_sender))
+        .expect("Failed to send message to database worker");
+
+    let worker_sender = worker_sender.clone();
+    tokio::spawn(async move {
+        write_result.result = reply_receiver
+            .await
+            .unwrap_or_else(|_| Err("Database worker dropped the write".to_string()));
+        let _ = worker_sender.send(WorkerMessageRequest::WriteCompleted(write_result));
+    });
+}
+
+async fn store_last_seen(
+    pool: &Pool<NoTls>,
+    user_id: &uuid::Uuid,
+    last_seen: &chrono::DateTime<Utc>,
+) {
+    let client = match pool.get().await {
+        Ok(client) => client,
+        Err(error) => {
+            println!(
+                "Error obtaining database client in message worker: {}",
+                error
+            );
+            return;
+        }
+    };
+
+    if let Err(error) = models::User::set_last_seen(user_id, last_seen, &client).await {
+        println!("Error storing last seen: {}", error);
+    }
+}
+
+// Presence goes to online co-members of the user's groups and to their direct message peers.
+// Both are loaded from the database, since an offline user is no longer cached.
+async fn broadcast_presence(
+    user_state: &mut HashMap<uuid::Uuid, ActiveUser>,
+    pool: &Pool<NoTls>,
+    presence: &PresenceMessage,
+) {
+    let client = match pool.get().await {
+        Ok(client) => client,
+        Err(error) => {
+            println!(
+                "Error obtaining database client in message worker: {}",
+                error
+            );
+            return;
+        }
+    };
+
+    let group_ids = match models::User::get_group_roles_of_user(&presence.user_id, &client).await {
+        Ok(group_roles) => group_roles
+            .into_iter()
+            .map(|(group_id, _)| group_id)
+            .collect::<Vec<uuid::Uuid>>(),
+        Err(error) => {
+            println!("Error loading groups for presence: {}", error);
+            return;
+        }
+    };
+
+    let peer_ids = match DirectChatMessage::get_peer_ids(&client, &presence.user_id).await {
+        Ok(peer_ids) => peer_ids,
+        Err(error) => {
+            println!("Error loading direct message peers for presence: {}", error);
+            return;
+        }
+    };
+
+    send_to_users(
+        user_state,
+        |user_id, active_user| {
+            *user_id != presence.user_id
+                && (peer_ids.contains(user_id)
+                    || group_ids
+                        .iter()
+                        .any(|group_id| active_user.groups.contains_key(group_id)))
+        },
+        &presence.clone().into(),
+    )
+    .await;
+}
+
+async fn flush_database_worker(database_sender: &mpsc::UnboundedSender<DatabaseWorkerRequest>) {
+    let (reply_sender, reply_receiver) = oneshot::channel();
+    database_sender
+        .send(DatabaseWorkerRequest::Flush(reply_sender))
+        .expect("Failed to send message to database worker");
+
+    if reply_receiver.await.is_err() {
+        println!("Database worker dropped flush reply");
+    }
+}
+
+async fn advance_sync_cursor(
+    pool: &Pool<NoTls>,
+    user_id: &uuid::Uuid,
+    device_id: &str,
+    acknowledged: SyncCheckpoint,
+) {
+    let client = match pool.get().await {
+        Ok(client) => client,
+        Err(error) => {
+            println!(
+                "Error obtaining database client in message worker: {}",
+                error
+            );
+            return;
+        }
+    };
+
+    let cursor = models::SyncCursor::from(acknowledged);
+    if let Err(error) = cursor.advance(user_id, device_id, &client).await {
+        println!("Error advancing sync cursor: {}", error);
+    }
+}
+
+// Sends the latest positions to every session that was sent something since its last
+// checkpoint. The client receives the checkpoint after everything sent before it, so its
+// acknowledgement confirms those messages arrived.
+async fn send_sync_checkpoints(
+    user_state: &mut HashMap<uuid::Uuid, ActiveUser>,
+    pool: &Pool<NoTls>,
+) {
+    let pending_sessions = user_state
+        .iter()
+        .flat_map(|(user_id, active_user)| {
+            active_user
+                .sync
+                .iter()
+                .filter(|(_, sync)| sync.pending)
+                .map(move |(session_id, _)| (*user_id, *session_id))
+        })
+        .collect::<Vec<(uuid::Uuid, uuid::Uuid)>>();
+    if pending_sessions.is_empty() {
+        return;
+    }
+
+    let client = match pool.get().await {
+        Ok(client) => client,
+        Err(error) => {
+            println!(
+                "Error obtaining database client in message worker: {}",
+                error
+            );
+            return;
+        }
+    };
+    let checkpoint = match models::SyncCursor::get_latest(&client).await {
+        Ok(latest) => SyncCheckpoint::from(latest),
+        Err(error) => {
+            println!("Error loading sync checkpoint: {}", error);
+            return;
+        }
+    };
+
+    let message = WebsocketMessage::SyncCheckpoint(checkpoint);
+    for (user_id, session_id) in pending_sessions {
+        if !send_to_session(user_state, &user_id, &session_id, &message).await {
+            continue;
+        }
+
+        if let Some(sync) = user_state
+            .get_mut(&user_id)
+            .and_then(|active_user| active_user.sync.get_mut(&session_id))
+        {
+            sync.pending = false;
+            sync.checkpoint = Some(checkpoint);
+        }
+    }
+}
+
+// Checkpoints only grow, so any acknowledgement up to the last checkpoint sent to the session
+// is one the session received.
+fn acknowledge_checkpoint(
+    user_state: &mut HashMap<uuid::Uuid, ActiveUser>,
+    user_id: &uuid::Uuid,
+    session_id: &uuid::Uuid,
+    acknowledged: SyncCheckpoint,
+) {
+    let Some(sync) = user_state
+        .get_mut(user_id)
+        .and_then(|active_user| active_user.sync.get_mut(session_id))
+    else {
+        return;
+    };
+
+    let Some(checkpoint) = sync.checkpoint else {
+        println!("Sync ack received before a checkpoint from {}", user_id);
+        return;
+    };
+    if !checkpoint.includes(&acknowledged) {
+        println!(
+            "Sync ack past the last checkpoint received from {}",
+            user_id
+        );
+        return;
+    }
+
+    if !sync
+        .acknowledged
+        .is_some_and(|previous| previous.includes(&acknowledged))
+    {
+        sync.acknowledged = Some(acknowledged);
+    }
+}
+
+// Sends everything the device missed since its cursor: direct messages, group chat and
+// events of the user's groups. A device without a cursor starts from the latest position.
+async fn replay_missed_messages(
+    user_state: &mut HashMap<uuid::Uuid, ActiveUser>,
+    pool: &Pool<NoTls>,
+    user_id: &uuid::Uuid,
+    session_id: &uuid::Uuid,
+    device_id: &str,
+) {
+    let groups = match user_state.get(user_id) {
+        Some(active_user) => active_user.groups.clone(),
+        None => return,
+    };
+
+    let client = match pool.get().await {
+        Ok(client) => client,
+        Err(error) => {
+            println!(
+                "Error obtaining database client in message worker: {}",
+                error
+            );
+            return;
+        }
+    };
+
+    let cursor = match models::SyncCursor::get(user_id, device_id, &client).await {
+        Ok(Some(cursor)) => cursor,
+        Ok(None) => {
+            if let Err(error) =
+                models::SyncCursor::advance_to_latest(user_id, device_id, &client).await
+            {
+                println!("Error advancing sync cursor: {}", error);
+            }
+            return;
+        }
+        Err(error) => {
+            println!("Error loading sync cursor: {}", error);
+            return;
+        }
+    };
+
+    let group_ids = groups.keys().copied().collect::<Vec<uuid::Uuid>>();
+    let missed = match load_missed_messages(&client, user_id, &groups, &group_ids, &cursor).await {
+        Ok(missed) => missed,
+        Err(error) => {
+            println!("Error loading missed messages: {}", error);
+            return;
+        }
+    };
+
+    for message in missed {
+        if !send_to_session(user_state, user_id, session_id, &message).await {
+            return;
+        }
+    }
+}
+
+async fn load_missed_messages(
+    client: &deadpool_postgres::Client<NoTls>,
+    user_id: &uuid::Uuid,
+    groups: &HashMap<uuid::Uuid, GroupRole>,
+    group_ids: &[uuid::Uuid],
+    cursor: &models::SyncCursor,
+) -> Result<Vec<WebsocketMessage>, tokio_postgres::Error> {
+    let mut missed: Vec<WebsocketMessage> = vec![];
+
+    missed.extend(
+        DirectChatMessage::get_received_since(client, user_id, cursor.direct_sequence)
+            .await?
+            .into_iter()
+            .map(|message| DirectChatMessageResponse::from(message).into()),
+    );
+
+    missed.extend(
+        GroupChatMessage::get_since(client, group_ids, cursor.group_sequence)
+            .await?
+            .into_iter()
+            .map(|message| GroupChatMessageResponse::from(message).into()),
+    );
+
+    for event in models::Event::get_since(user_id, group_ids, cursor.event_sequence, client).await?
+    {
+        let response: WebsocketMessageResponse = match serde_json::from_value(event.payload) {
+            Ok(response) => response,
+            Err(error) => {
+                println!("Error deserializing event {}: {}", event.id, error);
+                continue;
+            }
+        };
+
+        if let WebsocketMessageResponse::JoinGroup(join_group) = &response {
+            let can_approve = groups
+                .get(&join_group.group_id)
+                .is_some_and(|role| role.allows(Permission::ApproveJoins));
+            if !can_approve {
+                continue;
+            }
+        }
+
+        missed.push(WebsocketMessage::Response(response));
+    }
+
+    Ok(missed)
 }
 
 async fn send_group_message<T>(
     user_state: &mut HashMap<uuid::Uuid, ActiveUser>,
     group_chat_message: &T,
 ) where
-    T: Sized + Serialize + GroupId,
+    T: Clone + GroupId + Into<WebsocketMessage>,
 {
-    let mut failures = vec![];
-    for (id, session) in get_group_users(user_state, group_chat_message.get_group_id()) {
-        let serialized_message = serde_json::to_string(group_chat_message)
-            .expect("Failed to serialize group chat message");
+    let group_id = *group_chat_message.get_group_id();
+    send_to_users(
+        user_state,
+        |_, active_user| active_user.groups.contains_key(&group_id),
+        &group_chat_message.clone().into(),
+    )
+    .await;
+}
+
+async fn send_direct_chat_message(
+    user_state: &mut HashMap<uuid::Uuid, ActiveUser>,
+    direct_chat_message: &DirectChatMessageResponse,
+) {
+    send_to_user(
+        user_state,
+        &direct_chat_message.receiver_id,
+        &direct_chat_message.clone().into(),
+    )
+    .await;
+}
 
-        if session.text(serialized_message).await.is_err() {
-            failures.push(id.clone());
+// Sends to every session of the users matching the filter. Sessions that fail are marked as
+// failed and skipped from then on, the other sessions of the same user keep receiving.
+async fn send_to_users<F>(
+    user_state: &mut HashMap<uuid::Uuid, ActiveUser>,
+    filter: F,
+    message: &WebsocketMessage,
+) where
+    F: Fn(&uuid::Uuid, &ActiveUser) -> bool,
+{
+    let serialized_message =
+        serde_json::to_string(message).expect("Failed to serialize websocket message");
+
+    for (user_id, active_user) in user_state.iter_mut() {
+        if !filter(user_id, active_user) {
+            continue;
         }
-    }
 
-    for failure in failures {
-        println!("Failed to send websocket message. Session closing to ");
-        user_state.remove(&failure);
+        for (session_id, session) in active_user.sessions.iter_mut() {
+            if active_user.failed_sessions.contains(session_id) {
+                continue;
+            }
+
+            if session
+                .websocket_session
+                .text(serialized_message.clone())
+                .await
+                .is_err()
+            {
+                println!(
+                    "Failed to send websocket message to session {} of {}",
+                    session_id, user_id
+                );
+                active_user.failed_sessions.insert(*session_id);
+                continue;
+            }
+            active_user.sync.entry(*session_id).or_default().pending = true;
+        }
     }
 }
 
-async fn send_direct_chat_message(
+async fn send_to_user(
     user_state: &mut HashMap<uuid::Uuid, ActiveUser>,
-    direct_chat_message: &DirectChatMessageResponse,
+    user_id: &uuid::Uuid,
+    message: &WebsocketMessage,
 ) {
-    let user = if let Some(user) = user_state.get_mut(&direct_chat_message.receiver_id) {
-        user
-    } else {
-        return;
+    send_to_users(user_state, |id, _| id == user_id, message).await;
+}
+
+// Returns false when the session is gone or failed, in which case it is marked as failed.
+async fn send_to_session(
+    user_state: &mut HashMap<uuid::Uuid, ActiveUser>,
+    user_id: &uuid::Uuid,
+    session_id: &uuid::Uuid,
+    message: &WebsocketMessage,
+) -> bool {
+    let Some(active_user) = user_state.get_mut(user_id) else {
+        return false;
+    };
+    if active_user.failed_sessions.contains(session_id) {
+        return false;
+    }
+    let Some(session) = active_user.sessions.get_mut(session_id) else {
+        return false;
     };
 
-    if send_message(&direct_chat_message.clone().into(), user)
+    let serialized_message =
+        serde_json::to_string(message).expect("Failed to serialize websocket message");
+    if session
+        .websocket_session
+        .text(serialized_message)
         .await
         .is_err()
     {
-        user_state.remove(&direct_chat_message.receiver_id);
+        println!(
+            "Failed to send websocket message to session {} of {}",
+            session_id, user_id
+        );
+        active_user.failed_sessions.insert(*session_id);
+        return false;
     }
+    active_user.sync.entry(*session_id).or_default().pending = true;
+
+    true
 }
 
-fn get_group_users<'a>(
-    user_state: &'a mut HashMap<uuid::Uuid, ActiveUser>,
-    group_id: &uuid::Uuid,
-) -> Vec<(&'a uuid::Uuid, &'a mut actix_ws::Session)> {
-    let mut sessions = vec![];
-    for (id, active_user) in user_state {
-        if active_user.groups.contains(group_id) {
-            sessions.push((id, &mut active_user.websocket_session));
+// Acks and errors go back to the session the request came from, if it is known.
+async fn reply(
+    user_state: &mut HashMap<uuid::Uuid, ActiveUser>,
+    user_id: &uuid::Uuid,
+    session_id: Option<uuid::Uuid>,
+    message: &WebsocketMessage,
+) {
+    match session_id {
+        Some(session_id) => {
+            send_to_session(user_state, user_id, &session_id, message).await;
         }
+        None => send_to_user(user_state, user_id, message).await,
+    }
+}
+
+// Removes a single session, and the user once their last session is gone.
+fn remove_session(
+    user_state: &mut HashMap<uuid::Uuid, ActiveUser>,
+    user_id: &uuid::Uuid,
+    session_id: &uuid::Uuid,
+) -> Option<(ClientSession, SessionSync)> {
+    let active_user = user_state.get_mut(user_id)?;
+    let session = active_user.sessions.remove(session_id);
+    let sync = active_user.sync.remove(session_id).unwrap_or_default();
+    active_user.failed_sessions.remove(session_id);
+
+    if active_user.sessions.is_empty() {
+        user_state.remove(user_id);
     }
 
-    sessions
+    session.map(|session| (session, sync))
 }
 
 async fn insert_active_user_to_user_state(
     user_state: &mut HashMap<uuid::Uuid, ActiveUser>,
     id: uuid::Uuid,
-    session: actix_ws::Session,
+    session: ClientSession,
     pool: &Pool<NoTls>,
 ) {
+    if let Some(active_user) = user_state.get_mut(&id) {
+        active_user.sessions.insert(session.session_id, session);
+        return;
+    }
+
     let client_connection = if let Ok(client_connection) = pool.get().await {
         client_connection
     } else {
@@ -172,58 +1017,175 @@ async fn insert_active_user_to_user_state(
         return;
     };
 
-    let group_ids = if let Ok(group_ids) =
-        db::models::user::User::get_group_ids(&id, &client_connection).await
+    let group_roles = if let Ok(group_roles) =
+        db::models::user::User::get_group_roles_of_user(&id, &client_connection).await
     {
-        group_ids
+        group_roles
     } else {
-        println!("error obtaing group ids in worker state");
+        println!("error obtaing group roles in worker state");
         return;
     };
 
     user_state.insert(
         id,
         ActiveUser {
-            groups: group_ids,
-            websocket_session: session,
+            groups: group_roles.into_iter().collect(),
+            sessions: HashMap::from([(session.session_id, session)]),
+            failed_sessions: HashSet::new(),
+            sync: HashMap::new(),
         },
     );
 }
 
-async fn is_approver_valid(pool: &Pool<NoTls>, approve_join_message: &ApproveJoin) -> bool {
-    let client = match pool.get().await {
-        Ok(client) => client,
-        Err(error) => {
-            println!(
-                "Error obtaining database client in message worker: {}",
-                error
-            );
-            return false;
+async fn authorize_request(
+    user_state: &HashMap<uuid::Uuid, ActiveUser>,
+    pool: &Pool<NoTls>,
+    websocket_message: &WebsocketMessageRequest,
+) -> Result<(), (ErrorCode, String)> {
+    let sender_id = websocket_message.sender_id();
+
+    if let Some((group_id, permission)) = websocket_message.required_permission() {
+        let role = user_state
+            .get(&sender_id)
+            .and_then(|active_user| active_user.groups.get(&group_id))
+            .ok_or((
+                ErrorCode::NotMember,
+                "User is not member of the group".to_string(),
+            ))?;
+
+        if !role.allows(permission) {
+            return Err((
+                ErrorCode::Forbidden,
+                "User role does not allow this request".to_string(),
+            ));
         }
-    };
-    let maybe_group = match models::Group::get_by_id(&approve_join_message.group_id, &client).await
-    {
-        Ok(maybe_group) => maybe_group,
-        Err(error) => {
-            println!(
-                "Error obtaining database client in message worker: {}",
-                error
-            );
-            return false;
+
+        if let Some((group_id, list_id)) = websocket_message.list_id() {
+            authorize_list(pool, &group_id, &list_id).await?;
         }
-    };
 
-    let db_group = if let Some(group) = maybe_group {
-        group
-    } else {
-        println!("Group from ApproveJoin message not found");
-        return false;
+        return Ok(());
+    }
+
+    match websocket_message {
+        WebsocketMessageRequest::DirectChatMessage(direct_chat_message) => {
+            authorize_direct(
+                user_state,
+                pool,
+                &sender_id,
+                &direct_chat_message.receiver_id,
+            )
+            .await
+        }
+        WebsocketMessageRequest::Typing(TypingMessage {
+            conversation: Conversation::Direct { peer_id },
+            ..
+        }) => authorize_direct(user_state, pool, &sender_id, peer_id).await,
+        WebsocketMessageRequest::MarkRead(MarkReadRequest {
+            conversation: Conversation::Direct { peer_id },
+            ..
+        }) => authorize_direct(user_state, pool, &sender_id, peer_id).await,
+        WebsocketMessageRequest::ApproveJoin(approve_join) => {
+            let client = pool.get().await.map_err(database_error)?;
+            let role = models::User::get_group_role(
+                &approve_join.group_owner,
+                &approve_join.group_id,
+                &client,
+            )
+            .await
+            .map_err(database_error)?;
+
+            if !role.is_some_and(|role| role.allows(Permission::ApproveJoins)) {
+                return Err((
+                    ErrorCode::Forbidden,
+                    "User is not allowed to approve join requests".to_string(),
+                ));
+            }
+
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn authorize_list(
+    pool: &Pool<NoTls>,
+    group_id: &uuid::Uuid,
+    list_id: &uuid::Uuid,
+) -> Result<(), (ErrorCode, String)> {
+    let client = pool.get().await.map_err(database_error)?;
+    let list = models::List::get_by_id(list_id, &client)
+        .await
+        .map_err(database_error)?
+        .filter(|list| &list.group_id == group_id)
+        .ok_or((
+            ErrorCode::InvalidRequest,
+            "List does not belong to the group".to_string(),
+        ))?;
+
+    if list.archived {
+        return Err((ErrorCode::InvalidRequest, "List is archived".to_string()));
+    }
+
+    Ok(())
+}
+
+// Direct conversations are only allowed between users that share a group.
+async fn authorize_direct(
+    user_state: &HashMap<uuid::Uuid, ActiveUser>,
+    pool: &Pool<NoTls>,
+    sender_id: &uuid::Uuid,
+    receiver_id: &uuid::Uuid,
+) -> Result<(), (ErrorCode, String)> {
+    if receiver_id == sender_id {
+        return Err((
+            ErrorCode::InvalidRequest,
+            "Can not send a direct message to yourself".to_string(),
+        ));
+    }
+
+    let sender_groups = user_state
+        .get(sender_id)
+        .map(|active_user| &active_user.groups)
+        .ok_or((ErrorCode::Unauthorized, "User is not logged in".to_string()))?;
+
+    let receiver_group_ids = match user_state.get(receiver_id) {
+        Some(active_user) => active_user.groups.keys().copied().collect(),
+        None => {
+            let client = pool.get().await.map_err(database_error)?;
+            models::User::get_group_roles_of_user(receiver_id, &client)
+                .await
+                .map_err(database_error)?
+                .into_iter()
+                .map(|(group_id, _)| group_id)
+                .collect::<Vec<uuid::Uuid>>()
+        }
     };
 
-    if db_group.created_by_user != approve_join_message.group_owner {
-        println!("Invalid approve join request");
-        true
-    } else {
-        false
+    if !receiver_group_ids
+        .iter()
+        .any(|group_id| sender_groups.contains_key(group_id))
+    {
+        return Err((
+            ErrorCode::Forbidden,
+            "Users do not share a group".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn database_error<E: std::fmt::Display>(error: E) -> (ErrorCode, String) {
+    (ErrorCode::DatabaseError, error.to_string())
+}
+
+fn set_cached_role(
+    user_state: &mut HashMap<uuid::Uuid, ActiveUser>,
+    user_id: &uuid::Uuid,
+    group_id: &uuid::Uuid,
+    role: GroupRole,
+) {
+    if let Some(active_user) = user_state.get_mut(user_id) {
+        active_user.groups.insert(*group_id, role);
     }
 }